@@ -0,0 +1,82 @@
+use crate::{RenderSettings, luminance, render_rows, scene, to_byte};
+use std::{f32::consts::TAU, fs::File, io, path::Path};
+
+// hundredths of a second per frame
+const FRAME_DELAY: u16 = 4;
+
+/// Renders one full turn of the demo [`scene`] into a looping grayscale GIF.
+///
+/// Frames sample `time` in `[0, 2π)` so the last frame leads straight back into the first.
+pub fn render_gif(
+    frames: usize,
+    width: usize,
+    height: usize,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let gif_width = u16::try_from(width).map_err(io::Error::other)?;
+    let gif_height = u16::try_from(height).map_err(io::Error::other)?;
+    let palette: Vec<u8> = (0..=255).flat_map(|v| [v; 3]).collect();
+
+    let file = File::create(path)?;
+    let mut encoder =
+        gif::Encoder::new(file, gif_width, gif_height, &palette).map_err(io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+
+    let settings = RenderSettings::default();
+    for i in 0..frames {
+        let scene = scene(TAU * i as f32 / frames as f32);
+        let pixels: Vec<u8> = render_rows(&scene, &settings, width, height, 1.0, |_, pixels| {
+            pixels
+                .map(|color| to_byte(luminance(color)))
+                .collect::<Vec<u8>>()
+        })
+        .0
+        .concat();
+        let mut frame = gif::Frame::from_indexed_pixels(gif_width, gif_height, pixels, None);
+        frame.delay = FRAME_DELAY;
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_every_frame() {
+        let path = std::env::temp_dir().join(format!("donut-{}.gif", std::process::id()));
+        render_gif(5, 12, 8, &path).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (12, 8));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, FRAME_DELAY);
+            frames += 1;
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(frames, 5);
+    }
+
+    #[test]
+    fn first_frame_matches_the_rgba_export() {
+        let path = std::env::temp_dir().join(format!("donut-rgba-{}.gif", std::process::id()));
+        render_gif(2, 12, 8, &path).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        let frame = decoder.read_next_frame().unwrap().unwrap().buffer.to_vec();
+        std::fs::remove_file(&path).unwrap();
+
+        // the demo is lit in white, so every channel of the export equals the gray level
+        let rgba = crate::render_rgba(&scene(0.0), &RenderSettings::default(), 12, 8);
+        let red: Vec<u8> = rgba.chunks(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(frame, red);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{RenderSettings, Scene, check_symbols, luminance, render_text_progressive, symbol};
+use glam::Vec3;
+use std::io::Write;
+
+// longest color escape is "\x1b[38;2;255;255;255m"
+const COLOR_ESCAPE_LEN: usize = 19;
+const RESET: &str = "\x1b[0m";
+
+pub fn render_scene_ansi(
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+) -> String {
+    let mut buffer = String::new();
+    render_scene_ansi_into(
+        &mut buffer,
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+    );
+    buffer
+}
+
+/// Same as [`render_scene_ansi`], but replaces the contents of `buffer` to reuse its allocation.
+/// Returns true when [`RenderSettings::step_budget`] cut the frame short.
+pub fn render_scene_ansi_into(
+    buffer: &mut String,
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+) -> bool {
+    render_scene_ansi_progressive(
+        buffer,
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+        |_| {},
+    )
+}
+
+/// Same as [`render_scene_ansi_into`], but hands every intermediate frame of an
+/// [interlaced](RenderSettings::interlace) render to `on_pass` as soon as it is ready.
+pub fn render_scene_ansi_progressive(
+    buffer: &mut String,
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+    on_pass: impl FnMut(&str),
+) -> bool {
+    check_symbols(scene);
+    let max_char_len = scene.symbols.iter().map(|c| c.len_utf8()).max().unwrap();
+    render_text_progressive(
+        buffer,
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+        (COLOR_ESCAPE_LEN + max_char_len) * screen_width + RESET.len(),
+        |screen_y, pixels, row| {
+            let row_len = row.len();
+            let mut rest = row;
+            for (screen_x, color) in pixels.enumerate() {
+                let [r, g, b] = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0).to_array();
+                let symbol = symbol(scene, luminance(color), screen_x, screen_y);
+                write!(
+                    rest,
+                    "\x1b[38;2;{};{};{}m{symbol}",
+                    r as u8, g as u8, b as u8
+                )
+                .unwrap();
+            }
+            rest.write_all(RESET.as_bytes()).unwrap();
+            row_len - rest.len()
+        },
+        on_pass,
+    )
+}
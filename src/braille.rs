@@ -0,0 +1,103 @@
+use crate::{RenderSettings, Scene, luminance, render_rows, symbol_index};
+
+const BRAILLE_BLANK: u32 = 0x2800;
+// bit of the braille pattern for each dot, indexed by [y][x] inside the 2x4 cell
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Renders the scene with braille patterns, each character cell holds 2x4 dots
+/// that are raised where the intensity picks a symbol from the brighter half of `scene.symbols`,
+/// so the cutoff follows the ramp, its brightness table and dithering.
+pub fn render_scene_braille(
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+) -> String {
+    let cutoff = scene.symbols.len() / 2;
+    // a dot is half of the cell wide and a quarter of the cell high
+    let (dots, _) = render_rows(
+        scene,
+        settings,
+        screen_width * 2,
+        screen_height * 4,
+        screen_aspect * 2.0,
+        |dot_y, pixels| {
+            pixels
+                .enumerate()
+                .map(|(dot_x, color)| symbol_index(scene, luminance(color), dot_x, dot_y) >= cutoff)
+                .collect::<Vec<bool>>()
+        },
+    );
+
+    dots.chunks(4)
+        .map(|rows| {
+            (0..screen_width)
+                .map(|screen_x| {
+                    let mut pattern = BRAILLE_BLANK;
+                    for (row, bits) in rows.iter().zip(BRAILLE_DOTS) {
+                        for (dx, bit) in bits.into_iter().enumerate() {
+                            if row[screen_x * 2 + dx] {
+                                pattern |= bit;
+                            }
+                        }
+                    }
+                    char::from_u32(pattern).unwrap()
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Background, Sdf, scene};
+
+    fn render_background(intensity: f32) -> String {
+        render_with_ramp(intensity, scene(0.0).symbols, None)
+    }
+
+    fn render_with_ramp(
+        intensity: f32,
+        symbols: Vec<char>,
+        symbol_brightness: Option<Vec<f32>>,
+    ) -> String {
+        let scene = Scene {
+            scene: Vec::<Box<dyn Sdf>>::new().boxed(),
+            background: Background::Solid(intensity),
+            symbols,
+            symbol_brightness,
+            ..scene(0.0)
+        };
+        render_scene_braille(&scene, &RenderSettings::default(), 3, 2, 0.5)
+    }
+
+    #[test]
+    fn lit_and_empty_cells() {
+        assert_eq!(
+            render_background(1.0),
+            "\u{28ff}\u{28ff}\u{28ff}\n\u{28ff}\u{28ff}\u{28ff}"
+        );
+        assert_eq!(
+            render_background(0.0),
+            "\u{2800}\u{2800}\u{2800}\n\u{2800}\u{2800}\u{2800}"
+        );
+    }
+
+    #[test]
+    fn cutoff_follows_the_ramp() {
+        let blank = "\u{2800}\u{2800}\u{2800}\n\u{2800}\u{2800}\u{2800}";
+        let lit = "\u{28ff}\u{28ff}\u{28ff}\n\u{28ff}\u{28ff}\u{28ff}";
+        assert_eq!(render_background(0.4), blank);
+        // 0.4 picks the middle of a three symbol ramp
+        assert_eq!(render_with_ramp(0.4, vec![' ', '.', '@'], None), lit);
+        // a brightness table that calls '@' dim lowers the cutoff
+        assert_eq!(render_with_ramp(0.3, vec![' ', '@'], None), blank);
+        assert_eq!(
+            render_with_ramp(0.3, vec![' ', '@'], Some(vec![0.0, 0.2])),
+            lit
+        );
+    }
+}
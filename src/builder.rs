@@ -0,0 +1,266 @@
+use crate::{
+    Background, Light, Material, Projection, Scene, Sdf, SdfMaterial, ShadingMode, ToneMap,
+    check_symbols, wireframe_box,
+};
+use glam::Vec3;
+
+/// Chainable alternative to filling every [`Scene`] field by hand.
+///
+/// Shapes passed to [`SceneBuilder::add`] are combined into a union. When no light is added
+/// the scene gets a single white directional light.
+///
+/// The demo donut, built with the defaults:
+///
+/// ```
+/// use glam::{Mat4, Vec3, vec3};
+/// use terminal_donut_rs::*;
+///
+/// let time = 0.7;
+/// let donut = SceneBuilder::new()
+///     .add(SdfTransform {
+///         mat: Mat4::from_rotation_x(time) * Mat4::from_rotation_y(time),
+///         inner: [
+///             SdfSmoothUnion {
+///                 a: SdfSphere { center: Vec3::ZERO, radius: 7.0 },
+///                 b: SdfDonut::new(Vec3::ZERO, 10.0, 2.0),
+///                 k: 3.0,
+///             }
+///             .boxed(),
+///             SdfBox {
+///                 center: vec3(f32::sin(time * 2.0) * 3.0, 0.0, 0.0),
+///                 half_size: vec3(10.0, 3.0, 3.0),
+///             }
+///             .boxed(),
+///             SdfCylinder { center: Vec3::ZERO, radius: 2.0, half_height: 12.0 }.boxed(),
+///         ],
+///         distance_scale: 1.0,
+///     })
+///     .build();
+///
+/// let settings = RenderSettings::default();
+/// assert_eq!(
+///     render_scene(&donut, &settings, 40, 16, 0.5),
+///     render_scene(&scene(time), &settings, 40, 16, 0.5),
+/// );
+/// ```
+pub struct SceneBuilder {
+    shapes: Vec<Box<dyn Sdf>>,
+    // every setting except the shapes and the lights, starting from `Scene::new`
+    scene: Scene,
+    lights: Vec<Light>,
+    bounds_overlay: Option<f32>,
+}
+
+impl Default for SceneBuilder {
+    fn default() -> Self {
+        Self {
+            shapes: Vec::new(),
+            scene: Scene::new(Vec::<Box<dyn Sdf>>::new().boxed()),
+            lights: Vec::new(),
+            bounds_overlay: None,
+        }
+    }
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, sdf: impl Sdf + 'static) -> Self {
+        self.shapes.push(sdf.boxed());
+        self
+    }
+
+    pub fn camera_pos(mut self, camera_pos: Vec3) -> Self {
+        self.scene.camera_pos = camera_pos;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Vec3) -> Self {
+        self.scene.look_at = look_at;
+        self
+    }
+
+    pub fn camera_up(mut self, camera_up: Vec3) -> Self {
+        self.scene.camera_up = camera_up;
+        self
+    }
+
+    pub fn camera_roll(mut self, camera_roll: f32) -> Self {
+        self.scene.camera_roll = camera_roll;
+        self
+    }
+
+    /// Orthographic projection showing `size` units along the shorter screen side.
+    pub fn camera_size(mut self, size: f32) -> Self {
+        self.scene.projection = Projection::Orthographic { size };
+        self
+    }
+
+    pub fn projection(mut self, projection: Projection) -> Self {
+        self.scene.projection = projection;
+        self
+    }
+
+    pub fn depth_of_field(mut self, focal_distance: f32, aperture: f32) -> Self {
+        self.scene.focal_distance = focal_distance;
+        self.scene.aperture = aperture;
+        self
+    }
+
+    /// Adds a white directional light, `direction` points toward the light.
+    pub fn light_dir(self, direction: Vec3) -> Self {
+        self.light(Light::Directional {
+            direction,
+            color: Vec3::ONE,
+            intensity: 1.0,
+        })
+    }
+
+    pub fn light(mut self, light: Light) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    /// Ambient light for surfaces facing up and down, equal colors give a flat ambient.
+    pub fn ambient(mut self, sky: Vec3, ground: Vec3) -> Self {
+        self.scene.ambient_sky = sky;
+        self.scene.ambient_ground = ground;
+        self
+    }
+
+    pub fn diffuse(mut self, diffuse: f32) -> Self {
+        self.scene.diffuse = diffuse;
+        self
+    }
+
+    pub fn shadow_softness(mut self, shadow_softness: f32) -> Self {
+        self.scene.shadow_softness = shadow_softness;
+        self
+    }
+
+    pub fn ambient_occlusion(mut self, samples: usize, strength: f32) -> Self {
+        self.scene.ao_samples = samples;
+        self.scene.ao_strength = strength;
+        self
+    }
+
+    pub fn antialias(mut self, antialias: usize) -> Self {
+        self.scene.antialias = antialias;
+        self
+    }
+
+    pub fn jitter_frame(mut self, frame: Option<u32>) -> Self {
+        self.scene.jitter_frame = frame;
+        self
+    }
+
+    pub fn symbols(mut self, symbols: &str) -> Self {
+        self.scene.symbols = symbols.chars().collect();
+        self
+    }
+
+    pub fn symbol_brightness(mut self, brightness: Option<Vec<f32>>) -> Self {
+        self.scene.symbol_brightness = brightness;
+        self
+    }
+
+    pub fn dither(mut self, dither: bool) -> Self {
+        self.scene.dither = dither;
+        self
+    }
+
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.scene.gamma = gamma;
+        self
+    }
+
+    pub fn tone_map(mut self, tone_map: ToneMap) -> Self {
+        self.scene.tone_map = tone_map;
+        self
+    }
+
+    pub fn shading_mode(mut self, shading_mode: ShadingMode) -> Self {
+        self.scene.shading_mode = shading_mode;
+        self
+    }
+
+    pub fn specular(mut self, shininess: f32, strength: f32) -> Self {
+        self.scene.shininess = shininess;
+        self.scene.specular_strength = strength;
+        self
+    }
+
+    pub fn rim(mut self, power: f32, strength: f32) -> Self {
+        self.scene.rim_power = power;
+        self.scene.rim_strength = strength;
+        self
+    }
+
+    pub fn translucency(mut self, translucency: f32) -> Self {
+        self.scene.translucency = translucency;
+        self
+    }
+
+    pub fn fog(mut self, density: f32, color: Vec3) -> Self {
+        self.scene.fog_density = density;
+        self.scene.fog_color = color;
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.scene.background = background;
+        self
+    }
+
+    /// Debug aid drawing the bounding box of every added shape that reports one as glowing
+    /// edges `radius` thick, `None` turns it off.
+    pub fn show_bounds(mut self, radius: Option<f32>) -> Self {
+        self.bounds_overlay = radius;
+        self
+    }
+
+    pub fn build(mut self) -> Scene {
+        assert!(self.scene.antialias > 0, "antialias must be at least 1");
+        assert!(self.scene.gamma > 0.0, "gamma must be positive");
+        assert!(
+            self.scene.camera_pos != self.scene.look_at,
+            "camera_pos and look_at must differ"
+        );
+
+        if let Some(radius) = self.bounds_overlay {
+            let edges: Vec<_> = self
+                .shapes
+                .iter()
+                .filter_map(|shape| shape.bounds())
+                .flat_map(|(min, max)| wireframe_box((min + max) * 0.5, (max - min) * 0.5, radius))
+                .collect();
+            if !edges.is_empty() {
+                self.shapes.push(
+                    SdfMaterial {
+                        inner: edges,
+                        material: Material {
+                            emission: Vec3::ONE,
+                            ..Material::default()
+                        },
+                    }
+                    .boxed(),
+                );
+            }
+        }
+
+        let scene = Scene {
+            scene: self.shapes.boxed(),
+            lights: if self.lights.is_empty() {
+                self.scene.lights
+            } else {
+                self.lights
+            },
+            ..self.scene
+        };
+        check_symbols(&scene);
+        scene
+    }
+}
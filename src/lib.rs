@@ -1,9 +1,37 @@
+mod braille;
+mod builder;
+mod ppm;
 mod sdf;
+mod text;
+pub use braille::*;
+pub use builder::*;
+pub use ppm::*;
 pub use sdf::*;
+pub use text::*;
+
+#[cfg(feature = "color")]
+mod ansi;
+#[cfg(feature = "color")]
+pub use ansi::*;
+
+#[cfg(feature = "gif")]
+mod animation;
+#[cfg(feature = "gif")]
+pub use animation::*;
+
+#[cfg(feature = "png")]
+mod screenshot;
+#[cfg(feature = "png")]
+pub use screenshot::*;
+
+#[cfg(feature = "serde")]
+mod scene_desc;
+#[cfg(feature = "serde")]
+pub use scene_desc::*;
 
 #[cfg(feature = "wasm")]
 mod wasm {
-    use crate::{render_scene, scene};
+    use crate::{RenderSettings, render_scene, scene};
     use wasm_bindgen::prelude::wasm_bindgen;
     use web_sys::HtmlPreElement;
 
@@ -16,7 +44,13 @@ mod wasm {
     ) -> f32 {
         let Some(pre) = pre else { return -1.0 };
         let scene = scene(time);
-        let buffer = render_scene(&scene, screen_width, screen_height, 0.5);
+        let buffer = render_scene(
+            &scene,
+            &RenderSettings::default(),
+            screen_width,
+            screen_height,
+            0.5,
+        );
         pre.set_inner_html(&buffer);
         time
     }
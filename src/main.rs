@@ -1,42 +1,432 @@
-use crossterm::{cursor, event, execute, terminal};
+use crossterm::{cursor, event, execute, queue, terminal};
+use std::io::Write;
 use std::time::{Duration, Instant};
-use terminal_donut_rs::{render_scene, scene};
+use terminal_donut_rs::{Projection, RenderSettings, Scene, scene};
+
+#[cfg(feature = "color")]
+use terminal_donut_rs::render_scene_ansi_progressive as render_scene_progressive;
+#[cfg(not(feature = "color"))]
+use terminal_donut_rs::render_scene_progressive;
+
+/// Puts the terminal into raw mode with a hidden cursor and restores it when dropped,
+/// including when unwinding from a panic.
+struct TerminalGuard<W: Write> {
+    out: W,
+    alternate_screen: bool,
+    raw_mode: bool,
+}
+
+impl TerminalGuard<std::io::Stdout> {
+    fn new(alternate_screen: bool) -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        TerminalGuard::enter(std::io::stdout(), alternate_screen, true)
+    }
+}
+
+impl<W: Write> TerminalGuard<W> {
+    /// Hides the cursor on `out`, raw mode is switched off on drop only when `raw_mode` is set.
+    /// When writing to `out` fails the guard is dropped right away and undoes what was set up.
+    fn enter(out: W, alternate_screen: bool, raw_mode: bool) -> std::io::Result<Self> {
+        let mut guard = Self {
+            out,
+            alternate_screen: false,
+            raw_mode,
+        };
+        if alternate_screen {
+            execute!(guard.out, terminal::EnterAlternateScreen)?;
+            guard.alternate_screen = true;
+        }
+        execute!(guard.out, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl<W: Write> Drop for TerminalGuard<W> {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show);
+        if self.alternate_screen {
+            let _ = execute!(self.out, terminal::LeaveAlternateScreen);
+        }
+        if self.raw_mode {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+fn env_var<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+/// Keeps frames at least `1 / target_fps` apart and tracks the achieved frame rate.
+struct FrameTimer {
+    frame_duration: Option<Duration>,
+    last_frame: Option<Instant>,
+    fps: f32,
+}
+
+impl FrameTimer {
+    /// A target of 0 leaves the frame rate uncapped.
+    fn new(target_fps: f32) -> Self {
+        Self {
+            frame_duration: (target_fps > 0.0).then(|| Duration::from_secs_f32(1.0 / target_fps)),
+            last_frame: None,
+            fps: 0.0,
+        }
+    }
+
+    /// How long to wait at `now` before the next frame may start.
+    fn remaining(&self, now: Instant) -> Duration {
+        match (self.frame_duration, self.last_frame) {
+            (Some(frame_duration), Some(last_frame)) => {
+                frame_duration.saturating_sub(now - last_frame)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn frame_started(&mut self, now: Instant) {
+        if let Some(last_frame) = self.last_frame {
+            let elapsed = (now - last_frame).as_secs_f32();
+            if elapsed > 0.0 {
+                // smooth the counter so it stays readable
+                self.fps = if self.fps > 0.0 {
+                    self.fps * 0.9 + 0.1 / elapsed
+                } else {
+                    1.0 / elapsed
+                };
+            }
+        }
+        self.last_frame = Some(now);
+    }
+}
+
+const ORBIT_STEP: f32 = 0.1;
+const ZOOM_STEP: f32 = 1.1;
+
+/// Camera orbiting around the scene's `look_at` point, driven by the keyboard.
+struct Orbit {
+    yaw: f32,
+    pitch: f32,
+    zoom: f32,
+}
+
+impl Orbit {
+    fn new() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            zoom: 1.0,
+        }
+    }
+
+    /// Returns false when the key asks to quit.
+    fn handle_key(&mut self, code: event::KeyCode) -> bool {
+        match code {
+            event::KeyCode::Left => self.yaw -= ORBIT_STEP,
+            event::KeyCode::Right => self.yaw += ORBIT_STEP,
+            // stay short of the poles where the view direction flips
+            event::KeyCode::Up => self.pitch = (self.pitch + ORBIT_STEP).min(1.5),
+            event::KeyCode::Down => self.pitch = (self.pitch - ORBIT_STEP).max(-1.5),
+            event::KeyCode::Char('+') | event::KeyCode::Char('=') => self.zoom *= ZOOM_STEP,
+            event::KeyCode::Char('-') => self.zoom /= ZOOM_STEP,
+            event::KeyCode::Char('q') | event::KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn apply(&self, scene: &mut Scene) {
+        let offset = scene.camera_pos - scene.look_at;
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let direction = glam::vec3(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch);
+        match &mut scene.projection {
+            Projection::Orthographic { size } => {
+                *size /= self.zoom;
+                scene.camera_pos = scene.look_at + direction * offset.length();
+            }
+            Projection::Perspective { .. } => {
+                scene.camera_pos = scene.look_at + direction * offset.length() / self.zoom;
+            }
+        }
+    }
+}
+
+/// Width of a character cell divided by its height, taken from `DONUT_CELL_ASPECT` when set,
+/// otherwise measured from the terminal's pixel size when it reports one.
+fn cell_aspect() -> f32 {
+    if let Some(aspect) = env_var::<f32>("DONUT_CELL_ASPECT").filter(|aspect| *aspect > 0.0) {
+        return aspect;
+    }
+    match terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+            (size.width as f32 / size.columns as f32) / (size.height as f32 / size.rows as f32)
+        }
+        // typical terminal fonts are about twice as tall as they are wide
+        _ => 0.5,
+    }
+}
+
+/// Writes finished frames to the terminal, clearing it first whenever its size changed.
+struct Screen<W: Write> {
+    out: W,
+    size: Option<(u16, u16)>,
+    clear: bool,
+    frame: Vec<u8>,
+}
+
+impl<W: Write> Screen<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            size: None,
+            clear: false,
+            frame: Vec::new(),
+        }
+    }
+
+    /// Takes the terminal size for the following frames, returns true when it changed.
+    fn resize(&mut self, size: (u16, u16)) -> bool {
+        let resized = self.size != Some(size);
+        if resized {
+            // whatever was drawn outside the new frame area would otherwise stay on screen
+            self.clear = true;
+        }
+        self.size = Some(size);
+        resized
+    }
+
+    /// Draws `text` from the top left corner in a single write, lines and characters past the
+    /// current size are left out. `fps` is shown on the bottom line when given.
+    fn present(&mut self, text: &str, fps: Option<f32>) -> std::io::Result<()> {
+        let (width, height) = self.size.unwrap_or_default();
+        let frame = &mut self.frame;
+        frame.clear();
+        if self.clear {
+            queue!(frame, terminal::Clear(terminal::ClearType::All))?;
+            frame.reserve(text.len() + height as usize);
+            self.clear = false;
+        }
+        queue!(frame, cursor::MoveTo(0, 0))?;
+        for (line, s) in text.split('\n').take(height as usize).enumerate() {
+            // raw mode needs an explicit carriage return at the end of each line
+            if line > 0 {
+                frame.extend_from_slice(b"\r\n");
+            }
+            push_clamped(frame, s, width as usize);
+        }
+        if let Some(fps) = fps {
+            queue!(frame, cursor::MoveTo(0, height.saturating_sub(1)))?;
+            write!(frame, "{fps:.1} fps")?;
+        }
+        self.out.write_all(frame)?;
+        self.out.flush()
+    }
+}
+
+/// Appends the first `width` visible characters of `line`, color escapes are kept so the
+/// colors stay balanced.
+fn push_clamped(frame: &mut Vec<u8>, line: &str, width: usize) {
+    let mut visible = 0;
+    let mut in_escape = false;
+    let mut char_bytes = [0; 4];
+    for c in line.chars() {
+        let keep = if in_escape || c == '\x1b' {
+            // escapes like "\x1b[38;2;1;2;3m" end with a letter
+            in_escape = c == '\x1b' || !c.is_ascii_alphabetic();
+            true
+        } else {
+            visible += 1;
+            visible <= width
+        };
+        if keep {
+            frame.extend_from_slice(c.encode_utf8(&mut char_bytes).as_bytes());
+        }
+    }
+}
 
 fn main() {
-    terminal::enable_raw_mode().unwrap();
-    execute!(
-        std::io::stdout(),
-        terminal::EnterAlternateScreen,
-        cursor::Hide
-    )
-    .unwrap();
+    // --auto keeps the self rotating demo, otherwise the camera is controlled by the keyboard
+    let auto = std::env::args().skip(1).any(|arg| arg == "--auto");
+    let mut orbit = Orbit::new();
 
+    let _guard = TerminalGuard::new(true).unwrap();
+
+    // a slow scene then shows up unfinished instead of freezing the terminal
+    let settings = RenderSettings {
+        step_budget: env_var("DONUT_STEP_BUDGET"),
+        interlace: env_var::<u8>("DONUT_INTERLACE").is_some_and(|value| value != 0),
+        ..RenderSettings::default()
+    };
     let start_time = Instant::now();
+    let mut buffer = String::new();
+    let mut screen = Screen::new(std::io::stdout());
+    let mut aspect = 0.5;
+    let mut frame_index = 0;
+    let mut timer = FrameTimer::new(env_var("DONUT_FPS").unwrap_or(30.0));
+    let show_fps = env_var::<u8>("DONUT_SHOW_FPS").is_some_and(|value| value != 0);
 
-    loop {
-        if event::poll(Duration::from_millis(0)).unwrap() {
-            if let event::Event::Key(event::KeyEvent { code: _, .. }) = event::read().unwrap() {
-                break;
+    'frames: loop {
+        while event::poll(Duration::from_millis(0)).unwrap() {
+            if let event::Event::Key(key) = event::read().unwrap()
+                && key.kind != event::KeyEventKind::Release
+                && (auto || !orbit.handle_key(key.code))
+            {
+                break 'frames;
             }
         }
+        std::thread::sleep(timer.remaining(Instant::now()));
+        timer.frame_started(Instant::now());
+
         let (screen_width, screen_height) = crossterm::terminal::size().unwrap();
-        let time = (Instant::now() - start_time).as_secs_f32();
-        let scene = scene(time);
-        let buffer = render_scene(&scene, screen_width as usize, screen_height as usize, 0.5);
+        if screen.resize((screen_width, screen_height)) {
+            // a font change usually comes with a resize
+            aspect = cell_aspect();
+        }
+        let mut scene = if auto {
+            scene((Instant::now() - start_time).as_secs_f32())
+        } else {
+            let mut scene = scene(0.0);
+            orbit.apply(&mut scene);
+            scene
+        };
+        scene.jitter_frame = Some(frame_index);
+        frame_index = frame_index.wrapping_add(1);
+        render_scene_progressive(
+            &mut buffer,
+            &scene,
+            &settings,
+            screen_width as usize,
+            screen_height as usize,
+            aspect,
+            |buffer| {
+                let fps = show_fps.then_some(timer.fps);
+                screen.present(buffer, fps).unwrap();
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for (line, s) in buffer.split("\n").enumerate() {
-            execute!(std::io::stdout(), cursor::MoveTo(0, line as u16)).unwrap();
-            print!("{s}");
+    #[test]
+    fn guard_restores_the_terminal_on_panic() {
+        let mut out = Vec::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = TerminalGuard::enter(&mut out, true, false).unwrap();
+            panic!("frame failed");
+        }));
+        assert!(result.is_err());
+
+        let mut expected = Vec::new();
+        queue!(
+            expected,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        )
+        .unwrap();
+        assert_eq!(out, expected);
+    }
+
+    /// Fails the first write once `fail_after` bytes went through, later writes succeed again.
+    struct FailingWriter {
+        written: Vec<u8>,
+        fail_after: usize,
+        failed: bool,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if !self.failed && self.written.len() >= self.fail_after {
+                self.failed = true;
+                return Err(std::io::Error::other("terminal went away"));
+            }
+            self.written.write(buf)
         }
-        // print!("{buffer}");
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn guard_restores_the_terminal_when_entering_fails() {
+        let mut enter_alternate_screen = Vec::new();
+        queue!(enter_alternate_screen, terminal::EnterAlternateScreen).unwrap();
+        let mut out = FailingWriter {
+            written: Vec::new(),
+            fail_after: enter_alternate_screen.len(),
+            failed: false,
+        };
+        // hiding the cursor fails after the alternate screen was entered
+        assert!(TerminalGuard::enter(&mut out, true, false).is_err());
+
+        let mut expected = enter_alternate_screen;
+        queue!(expected, cursor::Show, terminal::LeaveAlternateScreen).unwrap();
+        assert_eq!(out.written, expected);
     }
 
-    execute!(
-        std::io::stdout(),
-        terminal::LeaveAlternateScreen,
-        cursor::Show
-    )
-    .unwrap();
+    #[test]
+    fn frame_timer_paces_frames() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut timer = FrameTimer::new(10.0);
+        assert_eq!(timer.remaining(start), Duration::ZERO);
+
+        timer.frame_started(start);
+        let remaining = timer.remaining(start + ms(30)).as_secs_f32();
+        assert!((remaining - 0.07).abs() < 1e-5, "{remaining}");
+        // a late frame starts right away
+        assert_eq!(timer.remaining(start + ms(150)), Duration::ZERO);
 
-    terminal::disable_raw_mode().unwrap();
+        timer.frame_started(start + ms(100));
+        assert!((timer.fps - 10.0).abs() < 1e-3, "{}", timer.fps);
+        timer.frame_started(start + ms(300));
+        assert!((timer.fps - 9.5).abs() < 1e-3, "{}", timer.fps);
+
+        let mut uncapped = FrameTimer::new(0.0);
+        uncapped.frame_started(start);
+        assert_eq!(uncapped.remaining(start), Duration::ZERO);
+    }
+
+    #[test]
+    fn screen_clears_and_clamps_after_a_resize() {
+        let mut screen = Screen::new(Vec::new());
+        let text = "abcd\nefgh";
+        assert!(screen.resize((4, 2)));
+        screen.present(text, None).unwrap();
+        assert!(!screen.resize((4, 2)));
+        screen.present(text, None).unwrap();
+        // the terminal shrank before the next frame was rendered at the new size
+        assert!(screen.resize((3, 1)));
+        screen
+            .present("\x1b[38;2;1;2;3mabcd\x1b[0m\nefgh", None)
+            .unwrap();
+
+        let mut expected = Vec::new();
+        queue!(
+            expected,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )
+        .unwrap();
+        expected.extend_from_slice(b"abcd\r\nefgh");
+        queue!(expected, cursor::MoveTo(0, 0)).unwrap();
+        expected.extend_from_slice(b"abcd\r\nefgh");
+        queue!(
+            expected,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )
+        .unwrap();
+        expected.extend_from_slice(b"\x1b[38;2;1;2;3mabc\x1b[0m");
+        assert_eq!(
+            String::from_utf8(screen.out).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+    }
 }
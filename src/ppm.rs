@@ -0,0 +1,85 @@
+use crate::{RenderSettings, Scene, luminance, render_rows, to_byte};
+use std::io::Write;
+
+/// Writes the scene as a binary grayscale P6 PPM with square pixels.
+pub fn render_to_ppm(
+    mut writer: impl Write,
+    scene: &Scene,
+    settings: &RenderSettings,
+    width: usize,
+    height: usize,
+) -> std::io::Result<()> {
+    let (rows, _) = render_rows(scene, settings, width, height, 1.0, |_, pixels| {
+        pixels
+            .flat_map(|color| [to_byte(luminance(color)); 3])
+            .collect::<Vec<u8>>()
+    });
+    write!(writer, "P6\n{width} {height}\n255\n")?;
+    for row in rows {
+        writer.write_all(&row)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Material, Sdf, SdfMaterial, SdfSphere, render_rgba, scene};
+    use glam::Vec3;
+
+    #[test]
+    fn writes_header_and_grayscale_pixels() {
+        let scene = Scene {
+            scene: SdfMaterial {
+                inner: SdfSphere {
+                    center: Vec3::ZERO,
+                    radius: 2.0,
+                },
+                material: Material {
+                    emission: Vec3::ONE,
+                    ..Material::default()
+                },
+            }
+            .boxed(),
+            ..scene(0.0)
+        };
+        let mut ppm = Vec::new();
+        render_to_ppm(&mut ppm, &scene, &RenderSettings::default(), 3, 3).unwrap();
+
+        let header = b"P6\n3 3\n255\n";
+        assert!(ppm.starts_with(header));
+        let pixels = &ppm[header.len()..];
+        assert_eq!(pixels.len(), 3 * 3 * 3);
+        // only the middle pixel sees the glowing sphere
+        assert_eq!(pixels[..3], [0; 3]);
+        assert_eq!(pixels[4 * 3..5 * 3], [255; 3]);
+        assert_eq!(pixels[8 * 3..], [0; 3]);
+    }
+
+    #[test]
+    fn pixels_match_the_rgba_export() {
+        let scene = Scene {
+            scene: SdfMaterial {
+                inner: SdfSphere {
+                    center: Vec3::ZERO,
+                    radius: 2.0,
+                },
+                material: Material {
+                    emission: Vec3::splat(0.5),
+                    ..Material::default()
+                },
+            }
+            .boxed(),
+            ..scene(0.0)
+        };
+        let settings = RenderSettings::default();
+        let mut ppm = Vec::new();
+        render_to_ppm(&mut ppm, &scene, &settings, 3, 3).unwrap();
+        let rgba = render_rgba(&scene, &settings, 3, 3);
+
+        // half intensity rounds up to 128 in both
+        let middle = ppm.len() - 5 * 3;
+        assert_eq!(ppm[middle..middle + 3], [128; 3]);
+        assert_eq!(ppm[middle..middle + 3], rgba[4 * 4..4 * 4 + 3]);
+    }
+}
@@ -0,0 +1,693 @@
+use crate::*;
+use glam::{IVec3, Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Serializable description of an sdf tree, turned into a renderable shape with [`SdfNode::to_sdf`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SdfNode {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    Box {
+        center: Vec3,
+        half_size: Vec3,
+    },
+    RoundedBox {
+        center: Vec3,
+        half_size: Vec3,
+        radius: f32,
+    },
+    Donut {
+        center: Vec3,
+        radius: f32,
+        tube_radius: f32,
+        axis: Vec3,
+    },
+    CappedTorus {
+        center: Vec3,
+        radius: f32,
+        tube_radius: f32,
+        angle: f32,
+    },
+    Cylinder {
+        center: Vec3,
+        radius: f32,
+        half_height: f32,
+    },
+    Capsule {
+        a: Vec3,
+        b: Vec3,
+        radius: f32,
+    },
+    Plane {
+        normal: Vec3,
+        offset: f32,
+    },
+    Cone {
+        center: Vec3,
+        angle: f32,
+        height: f32,
+    },
+    Ellipsoid {
+        center: Vec3,
+        radii: Vec3,
+    },
+    Octahedron {
+        center: Vec3,
+        size: f32,
+    },
+    TriPrism {
+        center: Vec3,
+        size: Vec2,
+    },
+    HexPrism {
+        center: Vec3,
+        radius: f32,
+        half_height: f32,
+    },
+    Pyramid {
+        center: Vec3,
+        base: f32,
+        height: f32,
+    },
+    HeightField {
+        heights: Vec<f32>,
+        columns: usize,
+        cell_size: f32,
+    },
+    Char {
+        ch: char,
+        position: Vec3,
+        pixel_size: f32,
+        depth: f32,
+    },
+    Text {
+        text: String,
+        position: Vec3,
+        pixel_size: f32,
+        depth: f32,
+    },
+    Vesica {
+        center: Vec3,
+        radius: f32,
+        separation: f32,
+    },
+    SolidAngle {
+        center: Vec3,
+        angle: f32,
+        radius: f32,
+    },
+    RoundCone {
+        a: Vec3,
+        b: Vec3,
+        r1: f32,
+        r2: f32,
+    },
+    Transform {
+        mat: Mat4,
+        inner: Box<SdfNode>,
+        distance_scale: f32,
+    },
+    Scale {
+        inner: Box<SdfNode>,
+        factor: f32,
+    },
+    Material {
+        inner: Box<SdfNode>,
+        material: Material,
+    },
+    Repeat {
+        inner: Box<SdfNode>,
+        period: Vec3,
+    },
+    RepeatLimited {
+        inner: Box<SdfNode>,
+        period: Vec3,
+        count: IVec3,
+    },
+    Twist {
+        inner: Box<SdfNode>,
+        amount: f32,
+    },
+    Round {
+        inner: Box<SdfNode>,
+        radius: f32,
+    },
+    Shell {
+        inner: Box<SdfNode>,
+        thickness: f32,
+    },
+    Mirror {
+        inner: Box<SdfNode>,
+        axis: Vec3,
+    },
+    Elongate {
+        inner: Box<SdfNode>,
+        amount: Vec3,
+    },
+    Bounded {
+        inner: Box<SdfNode>,
+        center: Vec3,
+        radius: f32,
+    },
+    Displace {
+        inner: Box<SdfNode>,
+        amplitude: f32,
+        frequency: f32,
+    },
+    ClipPlane {
+        inner: Box<SdfNode>,
+        normal: Vec3,
+        offset: f32,
+        interior: Material,
+    },
+    Lipschitz {
+        inner: Box<SdfNode>,
+        bound: f32,
+    },
+    Union(Vec<SdfNode>),
+    SmoothUnion {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+        k: f32,
+    },
+    Subtract {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+    },
+    SmoothSubtract {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+        k: f32,
+    },
+    Intersect {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+    },
+    SmoothIntersect {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+        k: f32,
+    },
+    Xor {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+    },
+    Interpolate {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+        t: f32,
+    },
+}
+
+impl SdfNode {
+    pub fn to_sdf(&self) -> Box<dyn Sdf> {
+        match self {
+            &SdfNode::Sphere { center, radius } => SdfSphere { center, radius }.boxed(),
+            &SdfNode::Box { center, half_size } => SdfBox { center, half_size }.boxed(),
+            &SdfNode::RoundedBox {
+                center,
+                half_size,
+                radius,
+            } => SdfRoundedBox {
+                center,
+                half_size,
+                radius,
+            }
+            .boxed(),
+            &SdfNode::Donut {
+                center,
+                radius,
+                tube_radius,
+                axis,
+            } => SdfDonut {
+                center,
+                radius,
+                tube_radius,
+                axis,
+            }
+            .boxed(),
+            &SdfNode::CappedTorus {
+                center,
+                radius,
+                tube_radius,
+                angle,
+            } => SdfCappedTorus {
+                center,
+                radius,
+                tube_radius,
+                angle,
+            }
+            .boxed(),
+            &SdfNode::Cylinder {
+                center,
+                radius,
+                half_height,
+            } => SdfCylinder {
+                center,
+                radius,
+                half_height,
+            }
+            .boxed(),
+            &SdfNode::Capsule { a, b, radius } => SdfCapsule { a, b, radius }.boxed(),
+            &SdfNode::Plane { normal, offset } => SdfPlane { normal, offset }.boxed(),
+            &SdfNode::Cone {
+                center,
+                angle,
+                height,
+            } => SdfCone {
+                center,
+                angle,
+                height,
+            }
+            .boxed(),
+            &SdfNode::Ellipsoid { center, radii } => SdfEllipsoid { center, radii }.boxed(),
+            &SdfNode::Octahedron { center, size } => SdfOctahedron { center, size }.boxed(),
+            &SdfNode::TriPrism { center, size } => SdfTriPrism { center, size }.boxed(),
+            &SdfNode::HexPrism {
+                center,
+                radius,
+                half_height,
+            } => SdfHexPrism {
+                center,
+                radius,
+                half_height,
+            }
+            .boxed(),
+            &SdfNode::Pyramid {
+                center,
+                base,
+                height,
+            } => SdfPyramid {
+                center,
+                base,
+                height,
+            }
+            .boxed(),
+            SdfNode::HeightField {
+                heights,
+                columns,
+                cell_size,
+            } => SdfHeightField::grid(heights.clone(), *columns, *cell_size).boxed(),
+            &SdfNode::Char {
+                ch,
+                position,
+                pixel_size,
+                depth,
+            } => SdfChar {
+                ch,
+                position,
+                pixel_size,
+                depth,
+            }
+            .boxed(),
+            SdfNode::Text {
+                text,
+                position,
+                pixel_size,
+                depth,
+            } => SdfText {
+                text: text.clone(),
+                position: *position,
+                pixel_size: *pixel_size,
+                depth: *depth,
+            }
+            .boxed(),
+            &SdfNode::Vesica {
+                center,
+                radius,
+                separation,
+            } => SdfVesica {
+                center,
+                radius,
+                separation,
+            }
+            .boxed(),
+            &SdfNode::SolidAngle {
+                center,
+                angle,
+                radius,
+            } => SdfSolidAngle {
+                center,
+                angle,
+                radius,
+            }
+            .boxed(),
+            &SdfNode::RoundCone { a, b, r1, r2 } => SdfRoundCone { a, b, r1, r2 }.boxed(),
+            SdfNode::Transform {
+                mat,
+                inner,
+                distance_scale,
+            } => SdfTransform {
+                mat: *mat,
+                inner: inner.to_sdf(),
+                distance_scale: *distance_scale,
+            }
+            .boxed(),
+            SdfNode::Scale { inner, factor } => SdfScale {
+                inner: inner.to_sdf(),
+                factor: *factor,
+            }
+            .boxed(),
+            SdfNode::Material { inner, material } => SdfMaterial {
+                inner: inner.to_sdf(),
+                material: *material,
+            }
+            .boxed(),
+            SdfNode::Repeat { inner, period } => SdfRepeat {
+                inner: inner.to_sdf(),
+                period: *period,
+            }
+            .boxed(),
+            SdfNode::RepeatLimited {
+                inner,
+                period,
+                count,
+            } => SdfRepeatLimited {
+                inner: inner.to_sdf(),
+                period: *period,
+                count: *count,
+            }
+            .boxed(),
+            SdfNode::Twist { inner, amount } => SdfTwist {
+                inner: inner.to_sdf(),
+                amount: *amount,
+            }
+            .boxed(),
+            SdfNode::Round { inner, radius } => SdfRound {
+                inner: inner.to_sdf(),
+                radius: *radius,
+            }
+            .boxed(),
+            SdfNode::Shell { inner, thickness } => SdfShell {
+                inner: inner.to_sdf(),
+                thickness: *thickness,
+            }
+            .boxed(),
+            SdfNode::Mirror { inner, axis } => SdfMirror {
+                inner: inner.to_sdf(),
+                axis: *axis,
+            }
+            .boxed(),
+            SdfNode::Elongate { inner, amount } => SdfElongate {
+                inner: inner.to_sdf(),
+                amount: *amount,
+            }
+            .boxed(),
+            SdfNode::Bounded {
+                inner,
+                center,
+                radius,
+            } => SdfBounded {
+                inner: inner.to_sdf(),
+                center: *center,
+                radius: *radius,
+            }
+            .boxed(),
+            SdfNode::Displace {
+                inner,
+                amplitude,
+                frequency,
+            } => SdfDisplace {
+                inner: inner.to_sdf(),
+                amplitude: *amplitude,
+                frequency: *frequency,
+            }
+            .boxed(),
+            SdfNode::ClipPlane {
+                inner,
+                normal,
+                offset,
+                interior,
+            } => SdfClipPlane {
+                inner: inner.to_sdf(),
+                normal: *normal,
+                offset: *offset,
+                interior: *interior,
+            }
+            .boxed(),
+            SdfNode::Lipschitz { inner, bound } => SdfLipschitz {
+                inner: inner.to_sdf(),
+                bound: *bound,
+            }
+            .boxed(),
+            SdfNode::Union(nodes) => nodes
+                .iter()
+                .map(SdfNode::to_sdf)
+                .collect::<Vec<_>>()
+                .boxed(),
+            SdfNode::SmoothUnion { a, b, k } => SdfSmoothUnion {
+                a: a.to_sdf(),
+                b: b.to_sdf(),
+                k: *k,
+            }
+            .boxed(),
+            SdfNode::Subtract { a, b } => a.to_sdf().subtract(b.to_sdf()).boxed(),
+            SdfNode::SmoothSubtract { a, b, k } => SdfSmoothSubtract {
+                a: a.to_sdf(),
+                b: b.to_sdf(),
+                k: *k,
+            }
+            .boxed(),
+            SdfNode::Intersect { a, b } => a.to_sdf().intersect(b.to_sdf()).boxed(),
+            SdfNode::SmoothIntersect { a, b, k } => SdfSmoothIntersect {
+                a: a.to_sdf(),
+                b: b.to_sdf(),
+                k: *k,
+            }
+            .boxed(),
+            SdfNode::Xor { a, b } => a.to_sdf().xor(b.to_sdf()).boxed(),
+            SdfNode::Interpolate { a, b, t } => SdfInterpolate {
+                a: a.to_sdf(),
+                b: b.to_sdf(),
+                t: *t,
+            }
+            .boxed(),
+        }
+    }
+}
+
+/// Serializable counterpart of [`Scene`]. Missing fields take the values [`Scene::new`] uses.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneDesc {
+    pub scene: SdfNode,
+    pub camera_pos: Vec3,
+    pub look_at: Vec3,
+    pub camera_up: Vec3,
+    pub camera_roll: f32,
+    pub projection: Projection,
+    pub focal_distance: f32,
+    pub aperture: f32,
+    pub lights: Vec<Light>,
+    pub ambient_sky: Vec3,
+    pub ambient_ground: Vec3,
+    pub diffuse: f32,
+    pub shadow_softness: f32,
+    pub ao_samples: usize,
+    pub ao_strength: f32,
+    pub antialias: usize,
+    pub jitter_frame: Option<u32>,
+    pub symbols: String,
+    pub symbol_brightness: Option<Vec<f32>>,
+    pub dither: bool,
+    pub gamma: f32,
+    pub tone_map: ToneMap,
+    pub shading_mode: ShadingMode,
+    pub shininess: f32,
+    pub specular_strength: f32,
+    pub rim_power: f32,
+    pub rim_strength: f32,
+    pub translucency: f32,
+    pub fog_density: f32,
+    pub fog_color: Vec3,
+    pub background: Background,
+}
+
+impl Default for SceneDesc {
+    fn default() -> Self {
+        let scene = SdfNode::Union(Vec::new());
+        let Scene {
+            scene: _,
+            camera_pos,
+            look_at,
+            camera_up,
+            camera_roll,
+            projection,
+            focal_distance,
+            aperture,
+            lights,
+            ambient_sky,
+            ambient_ground,
+            diffuse,
+            shadow_softness,
+            ao_samples,
+            ao_strength,
+            antialias,
+            jitter_frame,
+            symbols,
+            symbol_brightness,
+            dither,
+            gamma,
+            tone_map,
+            shading_mode,
+            shininess,
+            specular_strength,
+            rim_power,
+            rim_strength,
+            translucency,
+            fog_density,
+            fog_color,
+            background,
+        } = Scene::new(scene.to_sdf());
+        Self {
+            scene,
+            camera_pos,
+            look_at,
+            camera_up,
+            camera_roll,
+            projection,
+            focal_distance,
+            aperture,
+            lights,
+            ambient_sky,
+            ambient_ground,
+            diffuse,
+            shadow_softness,
+            ao_samples,
+            ao_strength,
+            antialias,
+            jitter_frame,
+            symbols: symbols.into_iter().collect(),
+            symbol_brightness,
+            dither,
+            gamma,
+            tone_map,
+            shading_mode,
+            shininess,
+            specular_strength,
+            rim_power,
+            rim_strength,
+            translucency,
+            fog_density,
+            fog_color,
+            background,
+        }
+    }
+}
+
+impl SceneDesc {
+    pub fn to_scene(&self) -> Scene {
+        Scene {
+            scene: self.scene.to_sdf(),
+            camera_pos: self.camera_pos,
+            look_at: self.look_at,
+            camera_up: self.camera_up,
+            camera_roll: self.camera_roll,
+            projection: self.projection,
+            focal_distance: self.focal_distance,
+            aperture: self.aperture,
+            lights: self.lights.clone(),
+            ambient_sky: self.ambient_sky,
+            ambient_ground: self.ambient_ground,
+            diffuse: self.diffuse,
+            shadow_softness: self.shadow_softness,
+            ao_samples: self.ao_samples,
+            ao_strength: self.ao_strength,
+            antialias: self.antialias,
+            jitter_frame: self.jitter_frame,
+            symbols: self.symbols.chars().collect(),
+            symbol_brightness: self.symbol_brightness.clone(),
+            dither: self.dither,
+            gamma: self.gamma,
+            tone_map: self.tone_map,
+            shading_mode: self.shading_mode,
+            shininess: self.shininess,
+            specular_strength: self.specular_strength,
+            rim_power: self.rim_power,
+            rim_strength: self.rim_strength,
+            translucency: self.translucency,
+            fog_density: self.fog_density,
+            fog_color: self.fog_color,
+            background: self.background,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec3;
+
+    #[test]
+    fn transformed_union_round_trips_through_json() {
+        let desc = SceneDesc {
+            scene: SdfNode::Transform {
+                mat: Mat4::from_translation(vec3(-2.0, 0.0, 1.0)),
+                inner: Box::new(SdfNode::Union(vec![
+                    SdfNode::Sphere {
+                        center: Vec3::ZERO,
+                        radius: 3.0,
+                    },
+                    SdfNode::Box {
+                        center: vec3(4.0, 0.0, 0.0),
+                        half_size: Vec3::splat(1.0),
+                    },
+                ])),
+                distance_scale: 1.0,
+            },
+            camera_roll: 0.3,
+            ..SceneDesc::default()
+        };
+        let json = serde_json::to_string(&desc).unwrap();
+        let loaded: SceneDesc = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, desc);
+
+        let scene = loaded.to_scene();
+        // the sphere is moved to (2, 0, -1) and the box to (6, 0, -1)
+        assert!((scene.scene.distance(vec3(2.0, 0.0, -1.0)) + 3.0).abs() < 1e-5);
+        assert!((scene.scene.distance(vec3(6.0, 0.0, -1.0)) + 1.0).abs() < 1e-5);
+        assert_eq!(scene.camera_roll, 0.3);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let json = r#"{"scene": {"Sphere": {"center": [0, 0, 0], "radius": 7}}}"#;
+        let loaded: SceneDesc = serde_json::from_str(json).unwrap();
+        let expected = SceneDesc {
+            scene: SdfNode::Sphere {
+                center: Vec3::ZERO,
+                radius: 7.0,
+            },
+            ..SceneDesc::default()
+        };
+        assert_eq!(loaded, expected);
+    }
+
+    #[test]
+    fn defaults_match_the_builder() {
+        let desc = SceneDesc {
+            scene: SdfNode::Sphere {
+                center: Vec3::ZERO,
+                radius: 7.0,
+            },
+            ..SceneDesc::default()
+        }
+        .to_scene();
+        let built = SceneBuilder::new()
+            .add(SdfSphere {
+                center: Vec3::ZERO,
+                radius: 7.0,
+            })
+            .build();
+        assert_eq!(desc.lights, built.lights);
+        assert_eq!(desc.symbols, built.symbols);
+        assert_eq!(desc.projection, built.projection);
+        let settings = RenderSettings::default();
+        assert_eq!(
+            render_scene(&desc, &settings, 40, 16, 0.5),
+            render_scene(&built, &settings, 40, 16, 0.5)
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{RenderSettings, Scene, render_rgba};
+use std::{fs::File, io, path::Path};
+
+/// Renders the scene with square pixels and saves it as an RGBA PNG, transparent where
+/// nothing was hit.
+pub fn save_png(
+    path: impl AsRef<Path>,
+    scene: &Scene,
+    settings: &RenderSettings,
+    width: usize,
+    height: usize,
+) -> io::Result<()> {
+    let png_width = u32::try_from(width).map_err(io::Error::other)?;
+    let png_height = u32::try_from(height).map_err(io::Error::other)?;
+    let pixels = render_rgba(scene, settings, width, height);
+
+    let file = io::BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, png_width, png_height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&pixels).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene;
+
+    #[test]
+    fn writes_a_png_of_the_requested_size() {
+        let path = std::env::temp_dir().join(format!("donut-{}.png", std::process::id()));
+        save_png(&path, &scene(0.0), &RenderSettings::default(), 21, 13).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(bytes[..8], *b"\x89PNG\r\n\x1a\n");
+        // the header chunk comes first
+        assert_eq!(bytes[12..16], *b"IHDR");
+        assert_eq!(bytes[16..20], 21u32.to_be_bytes());
+        assert_eq!(bytes[20..24], 13u32.to_be_bytes());
+    }
+}
@@ -1,24 +1,257 @@
-use glam::{Mat4, Vec3, vec3};
-use std::fmt::Write;
+use glam::{IVec3, Mat4, Vec2, Vec3, Vec4, vec3};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
-const MAX_STEPS: i32 = 100;
-const MAX_DISTANCE: f32 = 100.0;
-const EPSILON: f32 = 0.01;
-const SYMBOLS: &[u8] = b" .,:;i1tfLCG08@";
+pub const DEFAULT_SYMBOLS: &str = " .,:;i1tfLCG08@";
+/// Approximate relative ink coverage of each character in [`DEFAULT_SYMBOLS`],
+/// for use as [`Scene::symbol_brightness`].
+pub const DEFAULT_SYMBOL_BRIGHTNESS: [f32; 15] = [
+    0.0, 0.08, 0.1, 0.14, 0.17, 0.25, 0.3, 0.33, 0.36, 0.38, 0.47, 0.55, 0.62, 0.72, 1.0,
+];
 
-pub trait Sdf {
+/// Smallest box enclosing both boxes, unknown when either of them is.
+fn merge_bounds(a: Option<(Vec3, Vec3)>, b: Option<(Vec3, Vec3)>) -> Option<(Vec3, Vec3)> {
+    let ((a_min, a_max), (b_min, b_max)) = (a?, b?);
+    Some((a_min.min(b_min), a_max.max(b_max)))
+}
+
+// rays averaged over the lens for depth of field
+const LENS_SAMPLES: usize = 8;
+
+// 4x4 ordered dithering thresholds, in sixteenths
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+const AO_STEP: f32 = 0.3;
+// depth probed below the surface for translucency is TRANSLUCENCY_SAMPLES * TRANSLUCENCY_STEP
+const TRANSLUCENCY_STEP: f32 = 0.2;
+const TRANSLUCENCY_SAMPLES: usize = 5;
+const RELAXATION: f32 = 1.2;
+// distance from a bounding sphere below which the bounded shape is evaluated,
+// must stay well above the hit epsilon so the sphere itself is never hit
+const BOUNDING_MARGIN: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    pub max_steps: u32,
+    /// Far clip distance, rays give up after traveling this far.
+    pub max_distance: f32,
+    /// Near clip distance, camera rays start this far from the camera.
+    pub near: f32,
+    pub epsilon: f32,
+    /// Grows the hit threshold with the distance traveled, 0 keeps it at `epsilon`.
+    pub pixel_radius: f32,
+    /// How many times a ray may bounce off reflective surfaces.
+    pub max_bounces: u32,
+    /// Upper limit on the marching steps of camera and reflected rays spent on one frame.
+    /// Once it runs out the remaining pixels are left as background and the frame is reported
+    /// as partial. `None` never cuts a frame short.
+    pub step_budget: Option<u64>,
+    /// Progressive renderers first show every other row doubled, then fill in the rest.
+    /// The finished frame is the same either way.
+    pub interlace: bool,
+    pub normal_method: NormalMethod,
+    /// Offset of the sample points used to estimate surface normals.
+    pub normal_epsilon: f32,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            max_steps: 100,
+            max_distance: 100.0,
+            near: 0.0,
+            epsilon: 0.01,
+            pixel_radius: 0.0,
+            max_bounces: 2,
+            step_budget: None,
+            interlace: false,
+            normal_method: NormalMethod::Central,
+            normal_epsilon: 0.0001,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Material {
+    pub color: Vec3,
+    /// Fraction of the color taken from the reflected ray, 0 is fully matte.
+    pub reflectivity: f32,
+    /// Light given off by the surface itself, anything but black replaces the lit color.
+    pub emission: Vec3,
+}
+
+impl Material {
+    pub fn lerp(self, other: Material, t: f32) -> Material {
+        Material {
+            color: self.color.lerp(other.color, t),
+            reflectivity: self.reflectivity + (other.reflectivity - self.reflectivity) * t,
+            emission: self.emission.lerp(other.emission, t),
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            reflectivity: 0.0,
+            emission: Vec3::ZERO,
+        }
+    }
+}
+
+/// Signed distance field, negative inside the shape.
+///
+/// Shapes combine through the combinator methods:
+///
+/// ```
+/// use glam::{Vec3, vec3};
+/// use terminal_donut_rs::{Sdf, SdfBox, SdfSphere};
+///
+/// let shape = SdfSphere {
+///     center: Vec3::ZERO,
+///     radius: 2.0,
+/// }
+/// .subtract(SdfBox {
+///     center: vec3(2.0, 0.0, 0.0),
+///     half_size: Vec3::splat(1.5),
+/// })
+/// .translate(vec3(0.0, 1.0, 0.0));
+///
+/// // the box bites into the sphere from +X, the whole shape is moved up by one
+/// assert!(shape.distance(vec3(-1.0, 1.0, 0.0)) < 0.0);
+/// assert!(shape.distance(vec3(1.0, 1.0, 0.0)) > 0.0);
+/// assert!(shape.distance(vec3(-1.0, -1.5, 0.0)) > 0.0);
+/// ```
+pub trait Sdf: Send + Sync {
     fn distance(&self, pt: Vec3) -> f32;
 
+    /// Distance along with the material of the surface closest to `pt`.
+    /// Combinators pick the material of whichever operand determines the distance.
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        (self.distance(pt), Material::default())
+    }
+
+    /// Writes the distance of every point in `points` to the same index of `distances`.
+    /// Shapes can override it to process many points at once.
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        assert_eq!(points.len(), distances.len());
+        for (pt, distance) in points.iter().zip(distances) {
+            *distance = self.distance(*pt);
+        }
+    }
+
+    /// Axis aligned box `(min, max)` enclosing the shape, `None` when the shape is unbounded
+    /// or its extent is unknown.
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        None
+    }
+
     fn boxed(self) -> Box<dyn Sdf>
     where
         Self: Sized + 'static,
     {
         Box::new(self)
     }
+
+    fn union<O: Sdf>(self, other: O) -> SdfUnion<Self, O>
+    where
+        Self: Sized,
+    {
+        SdfUnion { a: self, b: other }
+    }
+
+    fn subtract<O: Sdf>(self, other: O) -> SdfSubtract<Self, O>
+    where
+        Self: Sized,
+    {
+        SdfSubtract { a: self, b: other }
+    }
+
+    fn intersect<O: Sdf>(self, other: O) -> SdfIntersect<Self, O>
+    where
+        Self: Sized,
+    {
+        SdfIntersect { a: self, b: other }
+    }
+
+    fn xor<O: Sdf>(self, other: O) -> SdfXor<Self, O>
+    where
+        Self: Sized,
+    {
+        SdfXor { a: self, b: other }
+    }
+
+    fn translate(self, offset: Vec3) -> SdfTransform<Self>
+    where
+        Self: Sized,
+    {
+        SdfTransform::translate(self, offset)
+    }
+}
+
+// points forwarded at once by combinators, their scratch space lives on the stack
+const BATCH_CHUNK: usize = 8;
+
+/// Calls `batch` with consecutive chunks of `points` and the matching part of `distances`,
+/// along with scratch space for as many distances.
+pub(crate) fn for_batch_chunks(
+    points: &[Vec3],
+    distances: &mut [f32],
+    mut batch: impl FnMut(&[Vec3], &mut [f32], &mut [f32]),
+) {
+    assert_eq!(points.len(), distances.len());
+    let mut scratch = [0.0; BATCH_CHUNK];
+    for (points, distances) in points
+        .chunks(BATCH_CHUNK)
+        .zip(distances.chunks_mut(BATCH_CHUNK))
+    {
+        batch(points, distances, &mut scratch[..points.len()]);
+    }
+}
+
+/// Batch distances of `inner` at every point moved by `to_local`, scaled by `distance_scale`.
+fn local_distance_batch(
+    inner: &impl Sdf,
+    points: &[Vec3],
+    distances: &mut [f32],
+    to_local: impl Fn(Vec3) -> Vec3,
+    distance_scale: f32,
+) {
+    for_batch_chunks(points, distances, |points, distances, _| {
+        let mut local = [Vec3::ZERO; BATCH_CHUNK];
+        let local = &mut local[..points.len()];
+        for (local, pt) in local.iter_mut().zip(points) {
+            *local = to_local(*pt);
+        }
+        inner.distance_batch(local, distances);
+        for distance in distances {
+            *distance *= distance_scale;
+        }
+    });
+}
+
+/// Batch distances of `a` combined with those of `b` by `combine`.
+fn combined_distance_batch(
+    a: &impl Sdf,
+    b: &impl Sdf,
+    points: &[Vec3],
+    distances: &mut [f32],
+    combine: impl Fn(f32, f32) -> f32,
+) {
+    for_batch_chunks(points, distances, |points, distances, b_distances| {
+        a.distance_batch(points, distances);
+        b.distance_batch(points, b_distances);
+        for (distance, b) in distances.iter_mut().zip(b_distances) {
+            *distance = combine(*distance, *b);
+        }
+    });
 }
 
 impl<I, T> Sdf for I
 where
+    I: Send + Sync,
     for<'a> &'a I: IntoIterator<Item = &'a T>,
     T: Sdf,
 {
@@ -29,12 +262,53 @@ where
         }
         distance
     }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let mut nearest = (f32::MAX, Material::default());
+        for inner in self.into_iter() {
+            let current = inner.distance_material(pt);
+            if current.0 < nearest.0 {
+                nearest = current;
+            }
+        }
+        nearest
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        for_batch_chunks(points, distances, |points, distances, inner_distances| {
+            distances.fill(f32::MAX);
+            for inner in self.into_iter() {
+                inner.distance_batch(points, inner_distances);
+                for (distance, inner) in distances.iter_mut().zip(&*inner_distances) {
+                    *distance = distance.min(*inner);
+                }
+            }
+        });
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let mut shapes = self.into_iter();
+        let first = shapes.next()?.bounds();
+        shapes.fold(first, |bounds, inner| merge_bounds(bounds, inner.bounds()))
+    }
 }
 
 impl Sdf for Box<dyn Sdf> {
     fn distance(&self, pt: Vec3) -> f32 {
         self.as_ref().distance(pt)
     }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        self.as_ref().distance_material(pt)
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        self.as_ref().distance_batch(points, distances)
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.as_ref().bounds()
+    }
 }
 
 pub struct SdfSphere {
@@ -45,6 +319,18 @@ impl Sdf for SdfSphere {
     fn distance(&self, pt: Vec3) -> f32 {
         (pt - self.center).length() - self.radius
     }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        assert_eq!(points.len(), distances.len());
+        let (center, radius) = (self.center, self.radius);
+        for (pt, distance) in points.iter().zip(distances) {
+            *distance = (*pt - center).length() - radius;
+        }
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.center - self.radius, self.center + self.radius))
+    }
 }
 
 pub struct SdfBox {
@@ -60,149 +346,4509 @@ impl Sdf for SdfBox {
 
         outside_distance + inside_distance
     }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.center - self.half_size, self.center + self.half_size))
+    }
+}
+
+/// Box with rounded edges, `half_size` is the outer extent including the rounding.
+pub struct SdfRoundedBox {
+    pub center: Vec3,
+    pub half_size: Vec3,
+    pub radius: f32,
+}
+
+impl Sdf for SdfRoundedBox {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let inner = SdfBox {
+            center: self.center,
+            half_size: self.half_size - self.radius,
+        };
+        inner.distance(pt) - self.radius
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.center - self.half_size, self.center + self.half_size))
+    }
 }
 
 pub struct SdfDonut {
     pub center: Vec3,
     pub radius: f32,
     pub tube_radius: f32,
+    pub axis: Vec3, // expected to be normalized
+}
+
+impl SdfDonut {
+    /// Donut lying in the XY plane.
+    pub fn new(center: Vec3, radius: f32, tube_radius: f32) -> Self {
+        Self {
+            center,
+            radius,
+            tube_radius,
+            axis: Vec3::Z,
+        }
+    }
 }
 
 impl Sdf for SdfDonut {
     fn distance(&self, pt: Vec3) -> f32 {
         let p = pt - self.center;
-        let q = glam::Vec2::new((p.x * p.x + p.y * p.y).sqrt() - self.radius, p.z);
+        let height = p.dot(self.axis);
+        let q = glam::Vec2::new((p - self.axis * height).length() - self.radius, height);
         q.length() - self.tube_radius
     }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        // the ring reaches furthest along the world axes that are most perpendicular to its axis
+        let ring = (Vec3::ONE - self.axis * self.axis)
+            .max(Vec3::ZERO)
+            .powf(0.5)
+            * self.radius;
+        let extent = ring + self.tube_radius;
+        Some((self.center - extent, self.center + extent))
+    }
 }
 
-pub struct SdfTransform<Inner> {
-    pub mat: Mat4,
-    pub inner: Inner,
+/// Part of a donut lying in the XY plane, the ring covers `angle` radians centered on +Y.
+pub struct SdfCappedTorus {
+    pub center: Vec3,
+    pub radius: f32,
+    pub tube_radius: f32,
+    pub angle: f32,
 }
 
-impl<Inner: Sdf> Sdf for SdfTransform<Inner> {
+impl Sdf for SdfCappedTorus {
     fn distance(&self, pt: Vec3) -> f32 {
-        self.inner.distance((self.mat * pt.extend(1.0)).truncate())
+        let p = pt - self.center;
+        let p = vec3(p.x.abs(), p.y, p.z);
+        let (sin, cos) = (self.angle * 0.5).sin_cos();
+        // past the end of the arc the closest ring point is the end point itself
+        let k = if cos * p.x > sin * p.y {
+            p.x * sin + p.y * cos
+        } else {
+            Vec2::new(p.x, p.y).length()
+        };
+        (p.length_squared() + self.radius * self.radius - 2.0 * self.radius * k)
+            .max(0.0)
+            .sqrt()
+            - self.tube_radius
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let outer = self.radius + self.tube_radius;
+        let extent = vec3(outer, outer, self.tube_radius);
+        Some((self.center - extent, self.center + extent))
     }
 }
 
-fn estimate_normal(scene: &impl Sdf, p: Vec3) -> Vec3 {
-    let eps = 0.0001;
-    let dx = eps * Vec3::X;
-    let dy = eps * Vec3::Y;
-    let dz = eps * Vec3::Z;
-    let normal = vec3(
-        scene.distance(p + dx) - scene.distance(p - dx),
-        scene.distance(p + dy) - scene.distance(p - dy),
-        scene.distance(p + dz) - scene.distance(p - dz),
-    );
-    normal.normalize()
+pub struct SdfCylinder {
+    pub center: Vec3,
+    pub radius: f32,
+    pub half_height: f32,
 }
 
-fn lambert_shading(normal: Vec3, light_dir: Vec3) -> f32 {
-    normal.dot(-light_dir).max(0.0)
+impl Sdf for SdfCylinder {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let p = pt - self.center;
+        let d = glam::Vec2::new(
+            (p.x * p.x + p.z * p.z).sqrt() - self.radius,
+            p.y.abs() - self.half_height,
+        );
+        d.x.max(d.y).min(0.0) + d.max(glam::Vec2::ZERO).length()
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let extent = vec3(self.radius, self.half_height, self.radius);
+        Some((self.center - extent, self.center + extent))
+    }
 }
 
-fn cast_ray(scene: &impl Sdf, start: Vec3, ray: Vec3, light_dir: Vec3) -> f32 {
-    let mut step = 0;
-    let mut total_distance_traveled = 0.0;
+pub struct SdfCapsule {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: f32,
+}
 
-    let mut current_point = start;
-    while step < MAX_STEPS && total_distance_traveled < MAX_DISTANCE {
-        let current_distance = scene.distance(current_point);
-        if current_distance < EPSILON {
-            let normal = estimate_normal(scene, current_point);
-            let shading = lambert_shading(normal, light_dir);
-            return 0.1 + shading * 0.9;
+impl Sdf for SdfCapsule {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let pa = pt - self.a;
+        let ba = self.b - self.a;
+        let len_sq = ba.length_squared();
+        // a == b degenerates into a sphere around a
+        let h = if len_sq > 0.0 {
+            (pa.dot(ba) / len_sq).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (pa - ba * h).length() - self.radius
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((
+            self.a.min(self.b) - self.radius,
+            self.a.max(self.b) + self.radius,
+        ))
+    }
+}
+
+/// Capsule whose radius changes from `r1` around `a` to `r2` around `b`, equal radii give
+/// an [`SdfCapsule`]. Neither end sphere may contain the other.
+pub struct SdfRoundCone {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub r1: f32,
+    pub r2: f32,
+}
+
+impl Sdf for SdfRoundCone {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let ba = self.b - self.a;
+        let pa = pt - self.a;
+        let len_sq = ba.length_squared();
+        if len_sq == 0.0 {
+            // both ends in one place, the larger sphere contains the smaller one
+            return pa.length() - self.r1.max(self.r2);
+        }
+        let radius_change = self.r1 - self.r2;
+        let a2 = len_sq - radius_change * radius_change;
+
+        // squared distances scaled by len_sq, to avoid square roots until the closest part is known
+        let y = pa.dot(ba);
+        let z = y - len_sq;
+        let x2 = (pa * len_sq - ba * y).length_squared();
+        let y2 = y * y * len_sq;
+        let z2 = z * z * len_sq;
+        let k = radius_change.signum() * radius_change * radius_change * x2;
+        if z.signum() * a2 * z2 > k {
+            // closest to the sphere around b
+            (x2 + z2).sqrt() / len_sq - self.r2
+        } else if y.signum() * a2 * y2 < k {
+            // closest to the sphere around a
+            (x2 + y2).sqrt() / len_sq - self.r1
+        } else {
+            // closest to the cone between them
+            ((x2 * a2 / len_sq).sqrt() + y * radius_change) / len_sq - self.r1
         }
-        total_distance_traveled += current_distance;
-        current_point += ray * (current_distance);
-        step += 1;
     }
 
-    0.0 // Pixel is in empty space
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((
+            (self.a - self.r1).min(self.b - self.r2),
+            (self.a + self.r1).max(self.b + self.r2),
+        ))
+    }
 }
 
-pub fn render_scene(
-    scene: &Scene,
-    screen_width: usize,
-    screen_height: usize,
-    screen_aspect: f32,
-) -> String {
-    let forward = (scene.look_at - scene.camera_pos).normalize_or(Vec3::NEG_Z);
-    let right = forward.cross(scene.camera_up).normalize_or(Vec3::X);
-    let up = forward.cross(right).normalize_or(Vec3::NEG_Y);
-
-    let (camera_width, camera_height) = if screen_width > screen_height {
-        (
-            scene.camera_size * screen_width as f32 / screen_height as f32 * screen_aspect,
-            scene.camera_size,
-        )
-    } else {
-        (
-            scene.camera_size,
-            scene.camera_size * screen_height as f32 / screen_width as f32 / screen_aspect,
-        )
+/// Thick line segment, the building block of wireframes.
+pub type SdfSegment = SdfCapsule;
+
+/// The 12 edges of a box as segments of the given thickness.
+pub fn wireframe_box(center: Vec3, half_size: Vec3, radius: f32) -> Vec<SdfSegment> {
+    let corner = |i: usize| {
+        let sign = vec3(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        center + half_size * sign
     };
+    // corners that differ in a single bit share an edge
+    (0..8)
+        .flat_map(|i| [1, 2, 4].map(|bit| (i, i | bit)))
+        .filter(|&(i, j)| i != j)
+        .map(|(i, j)| SdfSegment {
+            a: corner(i),
+            b: corner(j),
+            radius,
+        })
+        .collect()
+}
 
-    let mut buffer = String::with_capacity((screen_width + 1) * screen_height);
-    for screen_y in 0..screen_height {
-        if screen_y != 0 {
-            buffer.write_char('\n').unwrap();
+/// Grid of `rings` circles around the tube and `rings` circles along it, each made of `rings` segments.
+pub fn wireframe_donut(donut: &SdfDonut, rings: usize, radius: f32) -> Vec<SdfSegment> {
+    let (u_axis, v_axis) = donut.axis.any_orthonormal_pair();
+    let vertex = |i: usize, j: usize| {
+        let (sin_u, cos_u) = (std::f32::consts::TAU * i as f32 / rings as f32).sin_cos();
+        let (sin_v, cos_v) = (std::f32::consts::TAU * j as f32 / rings as f32).sin_cos();
+        let outward = u_axis * cos_u + v_axis * sin_u;
+        donut.center
+            + outward * (donut.radius + donut.tube_radius * cos_v)
+            + donut.axis * (donut.tube_radius * sin_v)
+    };
+    let mut segments = Vec::with_capacity(2 * rings * rings);
+    for i in 0..rings {
+        for j in 0..rings {
+            for (a, b) in [
+                (vertex(i, j), vertex((i + 1) % rings, j)),
+                (vertex(i, j), vertex(i, (j + 1) % rings)),
+            ] {
+                segments.push(SdfSegment { a, b, radius });
+            }
         }
-        for screen_x in 0..screen_width {
-            let x = scene.camera_pos
-                + right * (camera_width * (screen_x as f32 / (screen_width - 1) as f32 - 0.5));
-            let y = scene.camera_pos
-                + up * (camera_height * (screen_y as f32 / (screen_height - 1) as f32 - 0.5));
-            let intensity = cast_ray(&scene.scene, x + y, forward, scene.light_dir);
-            let char_index = ((intensity.clamp(0.0, 1.0) * (SYMBOLS.len() as f32)) as usize)
-                .clamp(0, SYMBOLS.len() - 1);
-            buffer.write_char(SYMBOLS[char_index] as char).unwrap();
+    }
+    segments
+}
+
+/// `count` shapes made by `shape(position, size)` with positions spread uniformly over the box
+/// from `min` to `max` and sizes drawn from `size`. The same `seed` always gives the same shapes.
+pub fn scatter<S: Sdf + 'static>(
+    count: usize,
+    seed: u64,
+    min: Vec3,
+    max: Vec3,
+    size: Range<f32>,
+    shape: impl Fn(Vec3, f32) -> S,
+) -> Vec<Box<dyn Sdf>> {
+    let mut state = seed;
+    // splitmix64, reduced to a float in [0, 1)
+    let mut random = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    };
+    (0..count)
+        .map(|_| {
+            let t = vec3(random(), random(), random());
+            let position = min + (max - min) * t;
+            let size = size.start + (size.end - size.start) * random();
+            shape(position, size).boxed()
+        })
+        .collect()
+}
+
+pub struct SdfPlane {
+    pub normal: Vec3, // expected to be normalized
+    pub offset: f32,
+}
+
+impl Sdf for SdfPlane {
+    fn distance(&self, pt: Vec3) -> f32 {
+        pt.dot(self.normal) + self.offset
+    }
+}
+
+/// Cone with the apex at `center` opening downwards along -Y, `angle` is the half-angle at the apex.
+pub struct SdfCone {
+    pub center: Vec3,
+    pub angle: f32,
+    pub height: f32,
+}
+
+impl Sdf for SdfCone {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let p = pt - self.center;
+        let (sin, cos) = self.angle.sin_cos();
+        // q is the base rim in the (radial, height) plane
+        let q = self.height * Vec2::new(sin / cos, -1.0);
+        let w = Vec2::new((p.x * p.x + p.z * p.z).sqrt(), p.y);
+        let a = w - q * (w.dot(q) / q.dot(q)).clamp(0.0, 1.0);
+        let b = w - q * Vec2::new((w.x / q.x).clamp(0.0, 1.0), 1.0);
+        let d = a.length_squared().min(b.length_squared());
+        let s = (w.y * q.x - w.x * q.y).max(q.y - w.y);
+        d.sqrt() * s.signum()
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let base_radius = self.height * self.angle.tan();
+        Some((
+            self.center - vec3(base_radius, self.height, base_radius),
+            self.center + vec3(base_radius, 0.0, base_radius),
+        ))
+    }
+}
+
+/// Uses the common bound-corrected approximation, which is not an exact distance away
+/// from the surface but is close enough to march with the usual step.
+pub struct SdfEllipsoid {
+    pub center: Vec3,
+    pub radii: Vec3,
+}
+
+impl Sdf for SdfEllipsoid {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let p = pt - self.center;
+        let k0 = (p / self.radii).length();
+        let k1 = (p / (self.radii * self.radii)).length();
+        if k1 == 0.0 {
+            return -self.radii.min_element();
         }
+        k0 * (k0 - 1.0) / k1
     }
 
-    buffer
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.center - self.radii, self.center + self.radii))
+    }
 }
 
-pub struct Scene {
-    pub scene: Box<dyn Sdf>,
-    pub camera_pos: Vec3,
-    pub look_at: Vec3,
-    pub camera_up: Vec3,
-    pub camera_size: f32,
-    pub light_dir: Vec3,
+/// Octahedron with vertices at `size` along each axis from `center`.
+pub struct SdfOctahedron {
+    pub center: Vec3,
+    pub size: f32,
 }
 
-pub fn scene(time: f32) -> Scene {
-    Scene {
-        scene: SdfTransform {
-            mat: Mat4::from_rotation_x(time) * Mat4::from_rotation_y(time),
-            inner: [
-                SdfSphere {
-                    center: Vec3::ZERO,
-                    radius: 7.0,
-                }
-                .boxed(),
-                SdfBox {
-                    center: vec3(f32::sin(time * 2.0) * 3.0, 0.0, 0.0),
-                    half_size: vec3(10.0, 3.0, 3.0),
-                }
-                .boxed(),
-                SdfDonut {
-                    center: Vec3::ZERO,
-                    radius: 10.0,
-                    tube_radius: 2.0,
-                }
-                .boxed(),
-            ],
+impl Sdf for SdfOctahedron {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let p = (pt - self.center).abs();
+        let m = p.x + p.y + p.z - self.size;
+        // rotate the point so the closest feature is always handled by the same branch
+        let q = if 3.0 * p.x < m {
+            p
+        } else if 3.0 * p.y < m {
+            vec3(p.y, p.z, p.x)
+        } else if 3.0 * p.z < m {
+            vec3(p.z, p.x, p.y)
+        } else {
+            return m / 3f32.sqrt();
+        };
+        let k = (0.5 * (q.z - q.y + self.size)).clamp(0.0, self.size);
+        vec3(q.x, q.y - self.size + k, q.z - k).length()
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.center - self.size, self.center + self.size))
+    }
+}
+
+/// Prism along Z with an equilateral triangle cross-section centered on `center`,
+/// `size.x` is half of the triangle side and `size.y` is half of the prism length.
+pub struct SdfTriPrism {
+    pub center: Vec3,
+    pub size: Vec2,
+}
+
+impl Sdf for SdfTriPrism {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let p = pt - self.center;
+        let r = self.size.x;
+        let k = 3f32.sqrt();
+        // exact distance to the triangle in the XY plane, apex pointing to +Y
+        let mut q = Vec2::new(p.x.abs() - r, p.y + r / k);
+        if q.x + k * q.y > 0.0 {
+            q = Vec2::new(q.x - k * q.y, -k * q.x - q.y) / 2.0;
         }
-        .boxed(),
-        camera_pos: vec3(0.0, 0.0, 20.0),
-        look_at: Vec3::ZERO,
-        camera_up: vec3(0.0, 1.0, 0.0),
-        camera_size: 25.0,
-        light_dir: vec3(1.0, -1.0, -1.0),
+        q.x -= q.x.clamp(-2.0 * r, 0.0);
+        let triangle = -q.length() * q.y.signum();
+        // extrude it along Z
+        let w = Vec2::new(triangle, p.z.abs() - self.size.y);
+        w.x.max(w.y).min(0.0) + w.max(Vec2::ZERO).length()
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let r = self.size.x;
+        let k = 3f32.sqrt();
+        Some((
+            self.center - vec3(r, r / k, self.size.y),
+            self.center + vec3(r, 2.0 * r / k, self.size.y),
+        ))
+    }
+}
+
+/// Hexagonal prism along Y with flat faces toward ±Z, `radius` is the apothem (distance to the flats),
+/// corners are at `radius * 2 / sqrt(3)`.
+pub struct SdfHexPrism {
+    pub center: Vec3,
+    pub radius: f32,
+    pub half_height: f32,
+}
+
+impl Sdf for SdfHexPrism {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let k = vec3(-0.866_025_4, 0.5, 0.577_350_3);
+        let p = (pt - self.center).abs();
+        // fold the hexagon into a single flat edge along +Z
+        let mut q = Vec2::new(p.x, p.z);
+        q -= 2.0 * k.truncate().dot(q).min(0.0) * k.truncate();
+        let edge = k.z * self.radius;
+        let d = Vec2::new(
+            (q - Vec2::new(q.x.clamp(-edge, edge), self.radius)).length()
+                * (q.y - self.radius).signum(),
+            p.y - self.half_height,
+        );
+        d.x.max(d.y).min(0.0) + d.max(Vec2::ZERO).length()
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        // corners are further out than the flat faces
+        let corner = self.radius * 2.0 / 3f32.sqrt();
+        let extent = vec3(corner, self.half_height, corner);
+        Some((self.center - extent, self.center + extent))
+    }
+}
+
+/// Square based pyramid pointing up along Y, `center` is the middle of the base
+/// and `base` is the side length of the base.
+pub struct SdfPyramid {
+    pub center: Vec3,
+    pub base: f32,
+    pub height: f32,
+}
+
+impl Sdf for SdfPyramid {
+    fn distance(&self, pt: Vec3) -> f32 {
+        // exact distance to a pyramid with a unit base, scaled to the requested size
+        let p = (pt - self.center) / self.base;
+        let h = self.height / self.base;
+        let m2 = h * h + 0.25;
+
+        // below the base the closest feature is the base square itself
+        if p.y <= 0.0 {
+            return (p.abs() - vec3(0.5, 0.0, 0.5)).max(Vec3::ZERO).length() * self.base;
+        }
+
+        // fold into the first quadrant and onto the face facing +X
+        let (mut x, y, mut z) = (p.x.abs(), p.y, p.z.abs());
+        if z > x {
+            std::mem::swap(&mut x, &mut z);
+        }
+        x -= 0.5;
+        z -= 0.5;
+
+        let q = vec3(z, h * y - 0.5 * x, h * x + 0.5 * y);
+        let s = (-q.x).max(0.0);
+        let t = ((q.y - 0.5 * z) / (m2 + 0.25)).clamp(0.0, 1.0);
+        let a = m2 * (q.x + s) * (q.x + s) + q.y * q.y;
+        let b = m2 * (q.x + 0.5 * t) * (q.x + 0.5 * t) + (q.y - m2 * t) * (q.y - m2 * t);
+        let d2 = if q.y.min(-q.x * m2 - q.y * 0.5) > 0.0 {
+            0.0
+        } else {
+            a.min(b)
+        };
+        let distance = ((d2 + q.z * q.z) / m2).sqrt() * q.z.max(-y).signum();
+        // inside, the base may be closer than any of the sides
+        distance.max(-y) * self.base
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let half_base = self.base * 0.5;
+        Some((
+            self.center - vec3(half_base, 0.0, half_base),
+            self.center + vec3(half_base, self.height, half_base),
+        ))
+    }
+}
+
+/// Part of a ball of `radius` around `center` inside a cone with its apex at `center` opening
+/// upwards along +Y, `angle` is the half-angle of the cone.
+pub struct SdfSolidAngle {
+    pub center: Vec3,
+    pub angle: f32,
+    pub radius: f32,
+}
+
+impl Sdf for SdfSolidAngle {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let p = pt - self.center;
+        let q = Vec2::new(Vec2::new(p.x, p.z).length(), p.y);
+        let (sin, cos) = self.angle.sin_cos();
+        let edge = Vec2::new(sin, cos);
+        // distance to the spherical cap and to the straight cone edge, signed by the side of the edge
+        let cap = q.length() - self.radius;
+        let side = (q - edge * q.dot(edge).clamp(0.0, self.radius)).length();
+        cap.max(side * (cos * q.x - sin * q.y).signum())
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.center - self.radius, self.center + self.radius))
+    }
+}
+
+/// Lens shaped intersection of two spheres of `radius` whose centers lie `separation` apart
+/// on the Z axis through `center`, `separation` must be less than twice the radius.
+pub struct SdfVesica {
+    pub center: Vec3,
+    pub radius: f32,
+    pub separation: f32,
+}
+
+impl Sdf for SdfVesica {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let p = pt - self.center;
+        // the shape is round around Z, so work in the plane of the axis and the radial direction
+        let axial = p.z.abs();
+        let radial = p.truncate().length();
+        let offset = self.separation * 0.5;
+        // radius of the sharp rim where the two spheres meet
+        let rim = (self.radius * self.radius - offset * offset).sqrt();
+        if (radial - rim) * offset > axial * rim {
+            Vec2::new(axial, radial - rim).length()
+        } else {
+            Vec2::new(axial + offset, radial).length() - self.radius
+        }
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let offset = self.separation * 0.5;
+        let rim = (self.radius * self.radius - offset * offset).sqrt();
+        let extent = vec3(rim, rim, self.radius - offset);
+        Some((self.center - extent, self.center + extent))
+    }
+}
+
+/// Terrain surface `y = height(x, z)`, solid below it.
+///
+/// The vertical gap to the surface overestimates the true distance on slopes, so it is divided
+/// by `sqrt(1 + lipschitz²)`. `lipschitz` has to bound the slope of `height` everywhere,
+/// otherwise marching can step through steep parts of the surface.
+pub struct SdfHeightField {
+    pub height: Box<dyn Fn(f32, f32) -> f32 + Send + Sync>,
+    pub lipschitz: f32,
+}
+
+impl SdfHeightField {
+    /// Bilinearly interpolated grid of heights stored row by row, `heights[row * columns + column]`
+    /// sits at `x = column * cell_size, z = row * cell_size`. Outside the grid the edge heights
+    /// continue outwards. The slope bound is derived from the grid.
+    pub fn grid(heights: Vec<f32>, columns: usize, cell_size: f32) -> Self {
+        assert!(
+            columns > 0 && !heights.is_empty() && heights.len().is_multiple_of(columns),
+            "heights must hold whole rows of `columns` values"
+        );
+        let rows = heights.len() / columns;
+        let at = move |column: usize, row: usize| heights[row * columns + column];
+
+        let mut max_step: f32 = 0.0;
+        for row in 0..rows {
+            for column in 0..columns {
+                if column + 1 < columns {
+                    max_step = max_step.max((at(column + 1, row) - at(column, row)).abs());
+                }
+                if row + 1 < rows {
+                    max_step = max_step.max((at(column, row + 1) - at(column, row)).abs());
+                }
+            }
+        }
+
+        let height = move |x: f32, z: f32| {
+            let u = (x / cell_size).clamp(0.0, (columns - 1) as f32);
+            let v = (z / cell_size).clamp(0.0, (rows - 1) as f32);
+            let (column, row) = (
+                (u as usize).min(columns.saturating_sub(2)),
+                (v as usize).min(rows.saturating_sub(2)),
+            );
+            let (next_column, next_row) = ((column + 1).min(columns - 1), (row + 1).min(rows - 1));
+            let (fu, fv) = (u - column as f32, v - row as f32);
+            let near = at(column, row) + (at(next_column, row) - at(column, row)) * fu;
+            let far =
+                at(column, next_row) + (at(next_column, next_row) - at(column, next_row)) * fu;
+            near + (far - near) * fv
+        };
+        Self {
+            height: Box::new(height),
+            // the gradient of a bilinear patch is bounded by its steepest edges along x and z
+            lipschitz: std::f32::consts::SQRT_2 * max_step / cell_size,
+        }
+    }
+}
+
+impl Sdf for SdfHeightField {
+    fn distance(&self, pt: Vec3) -> f32 {
+        (pt.y - (self.height)(pt.x, pt.z)) / (1.0 + self.lipschitz * self.lipschitz).sqrt()
+    }
+}
+
+/// `mat` is applied to the sample point, so it is the inverse of the transform of the shape.
+/// Distances of `inner` are multiplied by `distance_scale`, which has to compensate
+/// for any scaling in `mat`.
+pub struct SdfTransform<Inner> {
+    pub mat: Mat4,
+    pub inner: Inner,
+    pub distance_scale: f32,
+}
+
+impl<Inner> SdfTransform<Inner> {
+    pub fn translate(inner: Inner, offset: Vec3) -> Self {
+        Self {
+            mat: Mat4::from_translation(-offset),
+            inner,
+            distance_scale: 1.0,
+        }
+    }
+
+    pub fn rotate_x(inner: Inner, angle: f32) -> Self {
+        Self {
+            mat: Mat4::from_rotation_x(-angle),
+            inner,
+            distance_scale: 1.0,
+        }
+    }
+
+    pub fn rotate_y(inner: Inner, angle: f32) -> Self {
+        Self {
+            mat: Mat4::from_rotation_y(-angle),
+            inner,
+            distance_scale: 1.0,
+        }
+    }
+
+    pub fn rotate_z(inner: Inner, angle: f32) -> Self {
+        Self {
+            mat: Mat4::from_rotation_z(-angle),
+            inner,
+            distance_scale: 1.0,
+        }
+    }
+
+    /// With a non-uniform `factor` the distance is only a lower bound,
+    /// it is scaled by the smallest component to stay safe for marching.
+    pub fn scale(inner: Inner, factor: Vec3) -> Self {
+        Self {
+            mat: Mat4::from_scale(factor.recip()),
+            inner,
+            distance_scale: factor.abs().min_element(),
+        }
+    }
+
+    fn local_point(&self, pt: Vec3) -> Vec3 {
+        (self.mat * pt.extend(1.0)).truncate()
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfTransform<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(self.local_point(pt)) * self.distance_scale
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (distance, material) = self.inner.distance_material(self.local_point(pt));
+        (distance * self.distance_scale, material)
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        local_distance_batch(
+            &self.inner,
+            points,
+            distances,
+            |pt| self.local_point(pt),
+            self.distance_scale,
+        );
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let (min, max) = self.inner.bounds()?;
+        // mat maps into the local space of inner, so its inverse places the corners in the world
+        let to_world = self.mat.inverse();
+        let corners = (0..8).map(|corner| {
+            let select = glam::BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0);
+            to_world.transform_point3(Vec3::select(select, max, min))
+        });
+        Some(corners.fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), corner| (min.min(corner), max.max(corner)),
+        ))
+    }
+}
+
+/// Uniformly scales `inner` around the origin, keeping distances exact.
+pub struct SdfScale<Inner> {
+    pub inner: Inner,
+    pub factor: f32,
+}
+
+impl<Inner: Sdf> Sdf for SdfScale<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(pt / self.factor) * self.factor
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (distance, material) = self.inner.distance_material(pt / self.factor);
+        (distance * self.factor, material)
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        local_distance_batch(
+            &self.inner,
+            points,
+            distances,
+            |pt| pt / self.factor,
+            self.factor,
+        );
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let (min, max) = self.inner.bounds()?;
+        let (a, b) = (min * self.factor, max * self.factor);
+        Some((a.min(b), a.max(b)))
+    }
+}
+
+pub struct SdfMaterial<Inner> {
+    pub inner: Inner,
+    pub material: Material,
+}
+
+impl<Inner: Sdf> Sdf for SdfMaterial<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(pt)
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        (self.inner.distance(pt), self.material)
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        self.inner.distance_batch(points, distances);
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.inner.bounds()
+    }
+}
+
+pub struct SdfRepeat<Inner> {
+    pub inner: Inner,
+    pub period: Vec3, // zero component disables repetition along that axis
+}
+
+impl<Inner> SdfRepeat<Inner> {
+    fn cell_point(&self, pt: Vec3) -> Vec3 {
+        let cell = Vec3::select(
+            self.period.cmpne(Vec3::ZERO),
+            (pt / self.period).round(),
+            Vec3::ZERO,
+        );
+        pt - self.period * cell
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfRepeat<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(self.cell_point(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        self.inner.distance_material(self.cell_point(pt))
+    }
+}
+
+/// Repeats `inner` `count` times along each axis starting at the origin, counts below 1 give a single copy.
+pub struct SdfRepeatLimited<Inner> {
+    pub inner: Inner,
+    pub period: Vec3,
+    pub count: IVec3,
+}
+
+impl<Inner> SdfRepeatLimited<Inner> {
+    fn cell_point(&self, pt: Vec3) -> Vec3 {
+        let last_cell = (self.count - IVec3::ONE).max(IVec3::ZERO).as_vec3();
+        let cell = Vec3::select(
+            self.period.cmpne(Vec3::ZERO),
+            (pt / self.period).round().clamp(Vec3::ZERO, last_cell),
+            Vec3::ZERO,
+        );
+        pt - self.period * cell
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfRepeatLimited<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(self.cell_point(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        self.inner.distance_material(self.cell_point(pt))
+    }
+}
+
+/// Twists `inner` around the Y axis by `amount` radians per unit of height.
+/// Twisting stretches space, so the distance is only approximate and can overestimate
+/// the true one far from the axis. Wrap it in [`SdfLipschitz`] with a bound of
+/// `sqrt(1 + (amount * r)²)`, where `r` is the largest distance of the shape from the axis.
+pub struct SdfTwist<Inner> {
+    pub inner: Inner,
+    pub amount: f32,
+}
+
+impl<Inner> SdfTwist<Inner> {
+    fn twisted_point(&self, pt: Vec3) -> Vec3 {
+        let (sin, cos) = (self.amount * pt.y).sin_cos();
+        vec3(cos * pt.x - sin * pt.z, pt.y, sin * pt.x + cos * pt.z)
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfTwist<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(self.twisted_point(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        self.inner.distance_material(self.twisted_point(pt))
+    }
+}
+
+/// Reflects the half of space behind the plane through the origin with normal `axis`
+/// onto the front half, so only the front half of `inner` is visible, mirrored.
+pub struct SdfMirror<Inner> {
+    pub inner: Inner,
+    pub axis: Vec3, // expected to be normalized
+}
+
+impl<Inner> SdfMirror<Inner> {
+    fn mirrored_point(&self, pt: Vec3) -> Vec3 {
+        pt - self.axis * (2.0 * pt.dot(self.axis).min(0.0))
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfMirror<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(self.mirrored_point(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        self.inner.distance_material(self.mirrored_point(pt))
+    }
+}
+
+/// Stretches `inner` by inserting a straight section of `amount` in each direction
+/// along every axis, the ends keep their original shape.
+pub struct SdfElongate<Inner> {
+    pub inner: Inner,
+    pub amount: Vec3,
+}
+
+impl<Inner> SdfElongate<Inner> {
+    fn elongated_point(&self, pt: Vec3) -> Vec3 {
+        pt - pt.clamp(-self.amount, self.amount)
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfElongate<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(self.elongated_point(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        self.inner.distance_material(self.elongated_point(pt))
+    }
+}
+
+pub struct SdfRound<Inner> {
+    pub inner: Inner,
+    pub radius: f32,
+}
+
+impl<Inner: Sdf> Sdf for SdfRound<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(pt) - self.radius
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (distance, material) = self.inner.distance_material(pt);
+        (distance - self.radius, material)
+    }
+}
+
+/// Adds a bumpy sine pattern to the surface of `inner`.
+/// The displacement makes distances overestimate, so the result is scaled down
+/// by the steepest possible slope of the pattern to keep marching from overshooting.
+pub struct SdfDisplace<Inner> {
+    pub inner: Inner,
+    pub amplitude: f32,
+    pub frequency: f32,
+}
+
+impl<Inner> SdfDisplace<Inner> {
+    fn displaced(&self, distance: f32, pt: Vec3) -> f32 {
+        let p = pt * self.frequency;
+        let displacement = self.amplitude * p.x.sin() * p.y.sin() * p.z.sin();
+        let safety = 1.0 + (self.amplitude * self.frequency).abs() * 3f32.sqrt();
+        (distance - displacement) / safety
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfDisplace<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.displaced(self.inner.distance(pt), pt)
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (distance, material) = self.inner.distance_material(pt);
+        (self.displaced(distance, pt), material)
+    }
+}
+
+pub struct SdfShell<Inner> {
+    pub inner: Inner,
+    pub thickness: f32,
+}
+
+impl<Inner: Sdf> Sdf for SdfShell<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(pt).abs() - self.thickness
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (distance, material) = self.inner.distance_material(pt);
+        (distance.abs() - self.thickness, material)
+    }
+}
+
+/// Divides the distance of `inner` by `bound` so marching never steps past its surface.
+///
+/// Needed around shapes whose distance can overestimate the true one: [`SdfTwist`] and
+/// [`SdfTransform`] with a hand-built non-uniform `mat`. [`SdfDisplace`], [`SdfHeightField`]
+/// and [`SdfTransform::scale`] already compensate on their own.
+pub struct SdfLipschitz<Inner> {
+    pub inner: Inner,
+    pub bound: f32,
+}
+
+impl<Inner: Sdf> Sdf for SdfLipschitz<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.inner.distance(pt) / self.bound
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (distance, material) = self.inner.distance_material(pt);
+        (distance / self.bound, material)
+    }
+}
+
+/// Skips evaluating `inner` for points far outside the sphere that encloses it.
+/// Soft shadows passing near the sphere see the bound instead, so penumbras can get slightly darker.
+pub struct SdfBounded<Inner> {
+    pub inner: Inner,
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl<Inner> SdfBounded<Inner> {
+    fn bound_distance(&self, pt: Vec3) -> Option<f32> {
+        let distance = (pt - self.center).length() - self.radius;
+        (distance > BOUNDING_MARGIN).then_some(distance)
+    }
+}
+
+impl<Inner: Sdf> Sdf for SdfBounded<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.bound_distance(pt)
+            .unwrap_or_else(|| self.inner.distance(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        match self.bound_distance(pt) {
+            Some(distance) => (distance, Material::default()),
+            None => self.inner.distance_material(pt),
+        }
+    }
+}
+
+pub struct SdfUnion<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfUnion<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.a.distance(pt).min(self.b.distance(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let a = self.a.distance_material(pt);
+        let b = self.b.distance_material(pt);
+        if a.0 <= b.0 { a } else { b }
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        combined_distance_batch(&self.a, &self.b, points, distances, f32::min);
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        merge_bounds(self.a.bounds(), self.b.bounds())
+    }
+}
+
+fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    smooth_min_weighted(a, b, k).0
+}
+
+/// Smooth minimum along with how much `a` contributes to it, from 0 to 1.
+fn smooth_min_weighted(a: f32, b: f32, k: f32) -> (f32, f32) {
+    if k <= 0.0 {
+        return if a <= b { (a, 1.0) } else { (b, 0.0) };
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    (b + (a - b) * h - k * h * (1.0 - h), h)
+}
+
+pub struct SdfSmoothUnion<A, B> {
+    pub a: A,
+    pub b: B,
+    pub k: f32,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfSmoothUnion<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        smooth_min(self.a.distance(pt), self.b.distance(pt), self.k)
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (a, a_material) = self.a.distance_material(pt);
+        let (b, b_material) = self.b.distance_material(pt);
+        let (distance, weight) = smooth_min_weighted(a, b, self.k);
+        (distance, b_material.lerp(a_material, weight))
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        combined_distance_batch(&self.a, &self.b, points, distances, |a, b| {
+            smooth_min(a, b, self.k)
+        });
+    }
+}
+
+pub struct SdfSubtract<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfSubtract<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.a.distance(pt).max(-self.b.distance(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (a, a_material) = self.a.distance_material(pt);
+        let (b, b_material) = self.b.distance_material(pt);
+        if a >= -b {
+            (a, a_material)
+        } else {
+            (-b, b_material)
+        }
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        combined_distance_batch(&self.a, &self.b, points, distances, |a, b| a.max(-b));
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.a.bounds()
+    }
+}
+
+pub struct SdfSmoothSubtract<A, B> {
+    pub a: A,
+    pub b: B,
+    pub k: f32,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfSmoothSubtract<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        -smooth_min(-self.a.distance(pt), self.b.distance(pt), self.k)
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (a, a_material) = self.a.distance_material(pt);
+        let (b, b_material) = self.b.distance_material(pt);
+        let (distance, weight) = smooth_min_weighted(-a, b, self.k);
+        (-distance, b_material.lerp(a_material, weight))
+    }
+}
+
+pub struct SdfIntersect<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfIntersect<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.a.distance(pt).max(self.b.distance(pt))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let a = self.a.distance_material(pt);
+        let b = self.b.distance_material(pt);
+        if a.0 >= b.0 { a } else { b }
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        combined_distance_batch(&self.a, &self.b, points, distances, f32::max);
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        match (self.a.bounds(), self.b.bounds()) {
+            (Some((a_min, a_max)), Some((b_min, b_max))) => {
+                Some((a_min.max(b_min), a_max.min(b_max)))
+            }
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+pub struct SdfSmoothIntersect<A, B> {
+    pub a: A,
+    pub b: B,
+    pub k: f32,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfSmoothIntersect<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        -smooth_min(-self.a.distance(pt), -self.b.distance(pt), self.k)
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (a, a_material) = self.a.distance_material(pt);
+        let (b, b_material) = self.b.distance_material(pt);
+        let (distance, weight) = smooth_min_weighted(-a, -b, self.k);
+        (-distance, b_material.lerp(a_material, weight))
+    }
+}
+
+/// Cuts away the part of `inner` in front of the plane `pt.dot(normal) + offset = 0`,
+/// closing the cut with a solid face of the `interior` material.
+pub struct SdfClipPlane<Inner> {
+    pub inner: Inner,
+    pub normal: Vec3, // expected to be normalized
+    pub offset: f32,
+    pub interior: Material,
+}
+
+impl<Inner: Sdf> Sdf for SdfClipPlane<Inner> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let plane = pt.dot(self.normal) + self.offset;
+        self.inner.distance(pt).max(plane)
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let plane = pt.dot(self.normal) + self.offset;
+        let (distance, material) = self.inner.distance_material(pt);
+        if plane > distance {
+            (plane, self.interior)
+        } else {
+            (distance, material)
+        }
+    }
+}
+
+/// Symmetric difference, space inside exactly one of `a` and `b`.
+pub struct SdfXor<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfXor<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let a = self.a.distance(pt);
+        let b = self.b.distance(pt);
+        a.min(b).max(-a.max(b))
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let a = self.a.distance_material(pt);
+        let b = self.b.distance_material(pt);
+        let distance = a.0.min(b.0).max(-a.0.max(b.0));
+        // the surface belongs to whichever shape's boundary is closer
+        let material = if a.0.abs() <= b.0.abs() { a.1 } else { b.1 };
+        (distance, material)
+    }
+}
+
+/// Blends the distances of `a` and `b`, `t` of 0 gives `a` and 1 gives `b`.
+/// In between it is only an approximate distance and not a true morph, but it reads as a
+/// smooth transition when `t` is animated.
+pub struct SdfInterpolate<A, B> {
+    pub a: A,
+    pub b: B,
+    pub t: f32,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SdfInterpolate<A, B> {
+    fn distance(&self, pt: Vec3) -> f32 {
+        self.a.distance(pt) * (1.0 - self.t) + self.b.distance(pt) * self.t
+    }
+
+    fn distance_material(&self, pt: Vec3) -> (f32, Material) {
+        let (a, a_material) = self.a.distance_material(pt);
+        let (b, b_material) = self.b.distance_material(pt);
+        let distance = a * (1.0 - self.t) + b * self.t;
+        (distance, a_material.lerp(b_material, self.t))
+    }
+}
+
+fn estimate_normal(scene: &impl Sdf, settings: &RenderSettings, p: Vec3) -> Vec3 {
+    let eps = settings.normal_epsilon;
+    let dx = eps * Vec3::X;
+    let dy = eps * Vec3::Y;
+    let dz = eps * Vec3::Z;
+    let normal = match settings.normal_method {
+        NormalMethod::Central => {
+            let points = [p + dx, p - dx, p + dy, p - dy, p + dz, p - dz];
+            let mut d = [0.0; 6];
+            scene.distance_batch(&points, &mut d);
+            vec3(d[0] - d[1], d[2] - d[3], d[4] - d[5])
+        }
+        NormalMethod::Forward => {
+            let points = [p, p + dx, p + dy, p + dz];
+            let mut d = [0.0; 4];
+            scene.distance_batch(&points, &mut d);
+            vec3(d[1] - d[0], d[2] - d[0], d[3] - d[0])
+        }
+    };
+    normal.normalize()
+}
+
+fn lambert_shading(normal: Vec3, light_dir: Vec3) -> f32 {
+    normal.dot(light_dir).max(0.0)
+}
+
+fn phong_specular(normal: Vec3, light_dir: Vec3, ray: Vec3, shininess: f32) -> f32 {
+    let reflected = normal * (2.0 * normal.dot(light_dir)) - light_dir;
+    reflected.dot(-ray).max(0.0).powf(shininess)
+}
+
+fn soft_shadow(
+    scene: &impl Sdf,
+    settings: &RenderSettings,
+    start: Vec3,
+    light_dir: Vec3,
+    softness: f32,
+    light_distance: f32,
+) -> f32 {
+    let mut step = 0;
+    let mut total_distance_traveled = 0.0;
+    let mut shadow: f32 = 1.0;
+    let max_distance = settings.max_distance.min(light_distance);
+
+    while step < settings.max_steps && total_distance_traveled < max_distance {
+        let current_distance = scene.distance(start + light_dir * total_distance_traveled);
+        if current_distance < settings.epsilon {
+            return 0.0;
+        }
+        // the closer the ray passes to an occluder, the deeper the penumbra
+        shadow = shadow.min(current_distance / (softness * total_distance_traveled));
+        total_distance_traveled += current_distance;
+        step += 1;
+    }
+
+    shadow.clamp(0.0, 1.0)
+}
+
+fn ambient_occlusion(
+    scene: &impl Sdf,
+    point: Vec3,
+    normal: Vec3,
+    samples: usize,
+    strength: f32,
+) -> f32 {
+    let mut occlusion = 0.0;
+    let mut weight = 1.0;
+    for i in 1..=samples {
+        // in open space the field grows as fast as we move away from the surface
+        let offset = AO_STEP * i as f32;
+        occlusion += (offset - scene.distance(point + normal * offset)) * weight;
+        weight *= 0.5;
+    }
+    (1.0 - strength * occlusion).clamp(0.0, 1.0)
+}
+
+/// Fraction of light passing through the shape at `point`, from how deep the inside of the
+/// shape gets just below the surface. Thin features let most of the light through.
+fn transmittance(scene: &impl Sdf, point: Vec3, normal: Vec3) -> f32 {
+    let mut thickness = 0.0;
+    for i in 1..=TRANSLUCENCY_SAMPLES {
+        let offset = TRANSLUCENCY_STEP * i as f32;
+        thickness += (-scene.distance(point - normal * offset)).max(0.0);
+    }
+    (-thickness).exp()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub hit: bool,
+    /// Surface point on a hit, otherwise the point where marching gave up.
+    pub position: Vec3,
+    /// Surface normal on a hit, zero otherwise.
+    pub normal: Vec3,
+    pub steps: u32,
+    /// Distance traveled along the ray.
+    pub distance: f32,
+}
+
+/// Marches the ray until it hits a surface of `scene` or runs out of steps or distance.
+pub fn cast_ray(scene: &impl Sdf, settings: &RenderSettings, start: Vec3, ray: Vec3) -> RayHit {
+    march(scene, settings, start, ray, RELAXATION)
+}
+
+/// Sphere tracing that steps `relaxation` times the distance until it overshoots.
+fn march(
+    scene: &impl Sdf,
+    settings: &RenderSettings,
+    start: Vec3,
+    ray: Vec3,
+    mut relaxation: f32,
+) -> RayHit {
+    let mut step = 0;
+    let mut total_distance_traveled = settings.near;
+    let mut step_length = 0.0;
+    let mut previous_distance = 0.0;
+
+    let mut current_point = start + ray * settings.near;
+    while step < settings.max_steps && total_distance_traveled < settings.max_distance {
+        let current_distance = scene.distance(current_point);
+        // far away surfaces only need to be resolved to the size of a pixel
+        let hit_threshold = settings
+            .epsilon
+            .max(settings.pixel_radius * total_distance_traveled);
+        // a relaxed step overshot if the unbounding spheres of the last two points do not overlap
+        if relaxation > 1.0 && current_distance + previous_distance < step_length {
+            // step back into the safe sphere of the previous point and trace without relaxation
+            step_length -= relaxation * step_length;
+            relaxation = 1.0;
+        } else if current_distance < hit_threshold {
+            let position = start + ray * total_distance_traveled;
+            return RayHit {
+                hit: true,
+                position,
+                normal: estimate_normal(scene, settings, position),
+                steps: step,
+                distance: total_distance_traveled,
+            };
+        } else {
+            step_length = current_distance * relaxation;
+        }
+        previous_distance = current_distance;
+        total_distance_traveled += step_length;
+        current_point += ray * step_length;
+        step += 1;
+    }
+
+    RayHit {
+        hit: false,
+        position: current_point,
+        normal: Vec3::ZERO,
+        steps: step,
+        distance: total_distance_traveled,
+    }
+}
+
+fn shade(
+    scene: &Scene,
+    settings: &RenderSettings,
+    point: Vec3,
+    normal: Vec3,
+    material: Material,
+    ray: Vec3,
+) -> Vec3 {
+    let mut diffuse = Vec3::ZERO;
+    let mut specular = Vec3::ZERO;
+    for light in &scene.lights {
+        let (light_dir, light_color, light_distance) = light.incoming(point);
+        let lambert = lambert_shading(normal, light_dir);
+        if scene.translucency > 0.0 {
+            // light entering the far side of the shape and glowing through to this side
+            let back = (-normal).dot(light_dir).max(0.0);
+            if back > 0.0 {
+                diffuse += light_color
+                    * back
+                    * transmittance(&scene.scene, point, normal)
+                    * scene.translucency;
+            }
+        }
+        if lambert <= 0.0 {
+            continue;
+        }
+        let shadow = soft_shadow(
+            &scene.scene,
+            settings,
+            point + normal * (2.0 * settings.epsilon),
+            light_dir,
+            scene.shadow_softness,
+            light_distance,
+        );
+        let radiance = light_color * shadow;
+        diffuse += radiance * lambert;
+        specular += radiance
+            * phong_specular(normal, light_dir, ray, scene.shininess)
+            * scene.specular_strength;
+    }
+    let occlusion = ambient_occlusion(
+        &scene.scene,
+        point,
+        normal,
+        scene.ao_samples,
+        scene.ao_strength,
+    );
+    // hemisphere ambient, bounced light from the sky above and the ground below
+    let ambient = scene
+        .ambient_ground
+        .lerp(scene.ambient_sky, normal.y * 0.5 + 0.5);
+    // brightens silhouettes, where the surface turns away from the viewer
+    let rim = (1.0 - normal.dot(-ray)).max(0.0).powf(scene.rim_power) * scene.rim_strength;
+    material.color * (ambient + diffuse * scene.diffuse) * occlusion + specular + rim
+}
+
+/// Color of the ray in `xyz`, `w` is 1 when the ray hit the scene and 0 otherwise,
+/// together with the marching steps spent on it.
+fn ray_color(scene: &Scene, settings: &RenderSettings, start: Vec3, ray: Vec3) -> (Vec4, u32) {
+    trace(scene, settings, start, ray, settings.max_bounces)
+}
+
+fn trace(
+    scene: &Scene,
+    settings: &RenderSettings,
+    start: Vec3,
+    ray: Vec3,
+    bounces_left: u32,
+) -> (Vec4, u32) {
+    let RayHit {
+        hit,
+        position: point,
+        normal,
+        steps,
+        distance,
+    } = cast_ray(&scene.scene, settings, start, ray);
+    let mut total_steps = steps;
+    let color = match scene.shading_mode {
+        ShadingMode::StepHeat => Vec3::splat(steps as f32 / settings.max_steps as f32),
+        _ if !hit => Vec3::splat(scene.background.sample(ray)), // Pixel is in empty space
+        ShadingMode::Lambert => {
+            let (_, material) = scene.scene.distance_material(point);
+            let mut color = if material.emission != Vec3::ZERO {
+                // glowing surfaces look the same whichever way they face the lights
+                material.emission
+            } else {
+                shade(scene, settings, point, normal, material, ray)
+            };
+            if material.reflectivity > 0.0 && bounces_left > 0 {
+                let reflected_ray = ray - 2.0 * ray.dot(normal) * normal;
+                // clipping only applies to rays leaving the camera
+                let reflected_settings = RenderSettings {
+                    near: 0.0,
+                    ..*settings
+                };
+                let (reflected, reflected_steps) = trace(
+                    scene,
+                    &reflected_settings,
+                    point + normal * (2.0 * settings.epsilon),
+                    reflected_ray,
+                    bounces_left - 1,
+                );
+                total_steps += reflected_steps;
+                color = color.lerp(reflected.truncate(), material.reflectivity);
+            }
+            let fog = (-scene.fog_density * distance).exp();
+            color.lerp(scene.fog_color, 1.0 - fog)
+        }
+        ShadingMode::Normals => normal * 0.5 + 0.5,
+        ShadingMode::Depth => Vec3::splat(1.0 - distance / settings.max_distance),
+    };
+    (color.extend(if hit { 1.0 } else { 0.0 }), total_steps)
+}
+
+pub(crate) fn luminance(color: Vec3) -> f32 {
+    color.dot(vec3(0.2126, 0.7152, 0.0722))
+}
+
+/// Quantizes a channel in `[0, 1]` to the nearest of 256 levels, the same for every image export.
+pub(crate) fn to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+pub(crate) fn check_symbols(scene: &Scene) {
+    assert!(!scene.symbols.is_empty(), "symbol ramp must not be empty");
+    if let Some(brightness) = &scene.symbol_brightness {
+        assert_eq!(
+            brightness.len(),
+            scene.symbols.len(),
+            "symbol brightness table must have an entry per symbol"
+        );
+    }
+}
+
+/// Symbol for `intensity` at the given character cell, the cell only matters with [`Scene::dither`].
+pub(crate) fn symbol(scene: &Scene, intensity: f32, screen_x: usize, screen_y: usize) -> char {
+    scene.symbols[symbol_index(scene, intensity, screen_x, screen_y)]
+}
+
+/// Position in `scene.symbols` of the symbol picked for `intensity`, see [`symbol`].
+pub(crate) fn symbol_index(
+    scene: &Scene,
+    intensity: f32,
+    screen_x: usize,
+    screen_y: usize,
+) -> usize {
+    let symbols = &scene.symbols;
+    let intensity = if scene.dither {
+        // shift by up to half a ramp step in a fixed per-cell pattern
+        let threshold = (BAYER_4X4[screen_y % 4][screen_x % 4] as f32 + 0.5) / 16.0 - 0.5;
+        intensity + threshold / symbols.len() as f32
+    } else {
+        intensity
+    };
+    let intensity = intensity.clamp(0.0, 1.0);
+    match &scene.symbol_brightness {
+        Some(brightness) => (0..symbols.len())
+            .min_by(|&a, &b| {
+                (brightness[a] - intensity)
+                    .abs()
+                    .total_cmp(&(brightness[b] - intensity).abs())
+            })
+            .unwrap(),
+        None => ((intensity * (symbols.len() as f32)) as usize).clamp(0, symbols.len() - 1),
+    }
+}
+
+/// Right handed orthonormal camera axes `(right, up, forward)` for a camera at `pos` looking at
+/// `look_at`. `up` is made perpendicular to the view direction, when it is (nearly) parallel to
+/// it an arbitrary perpendicular axis is used instead.
+pub fn camera_basis(pos: Vec3, look_at: Vec3, up: Vec3) -> (Vec3, Vec3, Vec3) {
+    let forward = (look_at - pos).normalize_or(Vec3::NEG_Z);
+    let right = forward.cross(up);
+    let right = if right.length_squared() > 1e-8 {
+        right.normalize()
+    } else {
+        forward.any_orthonormal_vector()
+    };
+    let up = right.cross(forward).normalize_or(Vec3::Y);
+    (right, up, forward)
+}
+
+struct Camera {
+    pos: Vec3,
+    forward: Vec3,
+    right: Vec3,
+    up: Vec3,
+    width: f32,
+    height: f32,
+    projection: Projection,
+    screen_width: usize,
+    screen_height: usize,
+}
+
+impl Camera {
+    fn new(scene: &Scene, screen_width: usize, screen_height: usize, screen_aspect: f32) -> Self {
+        let (right, up, forward) = camera_basis(scene.camera_pos, scene.look_at, scene.camera_up);
+        let (sin, cos) = scene.camera_roll.sin_cos();
+        let (right, up) = (right * cos - up * sin, up * cos + right * sin);
+
+        let (width, height) = match scene.projection {
+            Projection::Orthographic { size } if screen_width > screen_height => (
+                size * screen_width as f32 / screen_height as f32 * screen_aspect,
+                size,
+            ),
+            Projection::Orthographic { size } => (
+                size,
+                size * screen_height as f32 / screen_width as f32 / screen_aspect,
+            ),
+            Projection::Perspective { fov_y_radians } => {
+                // extents of the view plane at unit distance from the camera
+                let height = 2.0 * (fov_y_radians * 0.5).tan();
+                (
+                    height * screen_width as f32 / screen_height as f32 * screen_aspect,
+                    height,
+                )
+            }
+        };
+
+        Self {
+            pos: scene.camera_pos,
+            forward,
+            right,
+            up,
+            width,
+            height,
+            projection: scene.projection,
+            screen_width,
+            screen_height,
+        }
+    }
+
+    /// Moves the origin of the camera ray by `lens` within the lens plane and aims it at the
+    /// point where the original ray meets the plane `focal_distance` in front of the camera.
+    fn lens_ray(&self, origin: Vec3, ray: Vec3, lens: Vec2, focal_distance: f32) -> (Vec3, Vec3) {
+        let focus = origin + ray * (focal_distance / ray.dot(self.forward));
+        let origin = origin + self.right * lens.x + self.up * lens.y;
+        (origin, (focus - origin).normalize())
+    }
+
+    fn ray(&self, screen_x: f32, screen_y: f32) -> (Vec3, Vec3) {
+        let offset_x = self.width * (screen_x / (self.screen_width - 1) as f32 - 0.5);
+        let offset_y = self.height * (screen_y / (self.screen_height - 1) as f32 - 0.5);
+        match self.projection {
+            Projection::Orthographic { .. } => (
+                self.pos + self.right * offset_x - self.up * offset_y,
+                self.forward,
+            ),
+            Projection::Perspective { .. } => (
+                self.pos,
+                (self.forward + self.right * offset_x - self.up * offset_y).normalize(),
+            ),
+        }
+    }
+}
+
+/// Element of the low-discrepancy Halton sequence in `[0, 1)`.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Position of the `index`th of [`LENS_SAMPLES`] rays on a lens disc of radius `aperture`,
+/// spread evenly over the disc along a golden angle spiral.
+fn lens_offset(index: usize, aperture: f32) -> Vec2 {
+    let radius = ((index as f32 + 0.5) / LENS_SAMPLES as f32).sqrt() * aperture;
+    let (sin, cos) = (index as f32 * 2.399_963).sin_cos();
+    Vec2::new(cos, sin) * radius
+}
+
+/// Sub-pixel offset of the camera rays for the given animation frame, zero without jitter.
+fn frame_jitter(jitter_frame: Option<u32>) -> Vec2 {
+    jitter_frame.map_or(Vec2::ZERO, |frame| {
+        Vec2::new(halton(frame + 1, 2), halton(frame + 1, 3)) - 0.5
+    })
+}
+
+/// Renders the scene row by row, `render_row` receives the row index and the colors of the row
+/// from left to right.
+/// With the `rayon` feature rows are rendered in parallel. The flag tells whether
+/// [`RenderSettings::step_budget`] ran out before the frame was finished.
+pub(crate) fn render_rows<Row: Send>(
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+    render_row: impl Fn(usize, &mut dyn Iterator<Item = Vec3>) -> Row + Sync,
+) -> (Vec<Row>, bool) {
+    render_rows_with_coverage(
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+        0..screen_width,
+        0..screen_height,
+        &AtomicU64::new(0),
+        |screen_y, pixels| render_row(screen_y, &mut pixels.map(Vec4::truncate)),
+    )
+}
+
+/// Renders text rows straight into `buffer`, joined with newlines, and calls `on_pass` with the
+/// text rendered so far. `render_row` writes one row of at most `max_row_len` bytes into the
+/// slice it gets and returns how many bytes it wrote.
+/// With [`RenderSettings::interlace`] the first pass renders the even rows and repeats each of
+/// them in place of the odd row below it, the second pass replaces those with the real odd rows.
+///
+/// The rows are rendered into fixed size slots behind the text in `buffer`, so they can be
+/// rendered in parallel and keep between the passes, and rendering a frame only allocates when
+/// `buffer` is too small for it.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn render_text_progressive(
+    buffer: &mut String,
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+    max_row_len: usize,
+    render_row: impl Fn(usize, &mut dyn Iterator<Item = Vec3>, &mut [u8]) -> usize + Sync,
+    mut on_pass: impl FnMut(&str),
+) -> bool {
+    // every slot starts with the length of its row
+    const LEN_SIZE: usize = size_of::<usize>();
+    let slot_len = LEN_SIZE + max_row_len;
+    let text_len = (max_row_len + 1) * screen_height;
+
+    let mut bytes = std::mem::take(buffer).into_bytes();
+    bytes.clear();
+    bytes.resize(text_len + slot_len * screen_height, 0);
+    let (text, slots) = bytes.split_at_mut(text_len);
+
+    // both interlaced passes draw from the budget of the one frame
+    let steps_spent = AtomicU64::new(0);
+    let pass = |slots: &mut [u8], first_row: usize, row_step: usize| {
+        let tracer = RowTracer::new(
+            scene,
+            settings,
+            screen_width,
+            screen_height,
+            screen_aspect,
+            0..screen_width,
+            &steps_spent,
+        );
+        let render_slot = |(screen_y, slot): (usize, &mut [u8])| {
+            let (len, row) = slot.split_at_mut(LEN_SIZE);
+            let pixels = &mut tracer.pixels(screen_y).map(Vec4::truncate);
+            len.copy_from_slice(&render_row(screen_y, pixels, row).to_ne_bytes());
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            slots
+                .par_chunks_mut(slot_len)
+                .enumerate()
+                .skip(first_row)
+                .step_by(row_step)
+                .for_each(render_slot);
+        }
+        #[cfg(not(feature = "rayon"))]
+        slots
+            .chunks_mut(slot_len)
+            .enumerate()
+            .skip(first_row)
+            .step_by(row_step)
+            .for_each(render_slot);
+        tracer.partial.into_inner()
+    };
+    // joins the rows into the text, `source_row` picks the slot shown on every line
+    let join = |text: &mut [u8], slots: &[u8], source_row: &dyn Fn(usize) -> usize| {
+        let mut end = 0;
+        for screen_y in 0..screen_height {
+            if screen_y > 0 {
+                text[end] = b'\n';
+                end += 1;
+            }
+            let (len, row) = slots[source_row(screen_y) * slot_len..].split_at(LEN_SIZE);
+            let len = usize::from_ne_bytes(len.try_into().unwrap());
+            text[end..end + len].copy_from_slice(&row[..len]);
+            end += len;
+        }
+        end
+    };
+
+    let partial = if settings.interlace {
+        let even_partial = pass(slots, 0, 2);
+        let end = join(text, slots, &|screen_y| screen_y & !1);
+        on_pass(std::str::from_utf8(&text[..end]).unwrap());
+        let odd_partial = pass(slots, 1, 2);
+        even_partial || odd_partial
+    } else {
+        pass(slots, 0, 1)
+    };
+    let end = join(text, slots, &|screen_y| screen_y);
+    on_pass(std::str::from_utf8(&text[..end]).unwrap());
+
+    bytes.truncate(end);
+    *buffer = String::from_utf8(bytes).unwrap();
+    partial
+}
+
+/// Like [`render_rows`], but each pixel also carries in `w` the fraction of its rays that hit.
+/// Only the pixels inside `columns` and `rows` of the full frame are rendered.
+/// `steps_spent` is checked against [`RenderSettings::step_budget`], passes rendering parts of
+/// the same frame share it.
+#[allow(clippy::too_many_arguments)]
+fn render_rows_with_coverage<Row: Send>(
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+    columns: Range<usize>,
+    rows: impl Iterator<Item = usize>,
+    steps_spent: &AtomicU64,
+    render_row: impl Fn(usize, &mut dyn Iterator<Item = Vec4>) -> Row + Sync,
+) -> (Vec<Row>, bool) {
+    let tracer = RowTracer::new(
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+        columns,
+        steps_spent,
+    );
+    let rows = map_rows(rows, |screen_y| {
+        render_row(screen_y, &mut tracer.pixels(screen_y))
+    });
+    (rows, tracer.partial.into_inner())
+}
+
+/// Traces the pixels of single rows of a frame, each pixel carries in `w` the fraction of its
+/// rays that hit.
+struct RowTracer<'a> {
+    scene: &'a Scene,
+    settings: &'a RenderSettings,
+    camera: Camera,
+    jitter: Vec2,
+    columns: Range<usize>,
+    steps_spent: &'a AtomicU64,
+    /// Set once [`RenderSettings::step_budget`] ran out.
+    partial: AtomicBool,
+}
+
+impl<'a> RowTracer<'a> {
+    fn new(
+        scene: &'a Scene,
+        settings: &'a RenderSettings,
+        screen_width: usize,
+        screen_height: usize,
+        screen_aspect: f32,
+        columns: Range<usize>,
+        steps_spent: &'a AtomicU64,
+    ) -> Self {
+        Self {
+            scene,
+            settings,
+            camera: Camera::new(scene, screen_width, screen_height, screen_aspect),
+            jitter: frame_jitter(scene.jitter_frame),
+            columns,
+            steps_spent,
+            partial: AtomicBool::new(false),
+        }
+    }
+
+    fn sample(&self, screen_x: f32, screen_y: f32) -> Vec4 {
+        let (scene, settings, steps_spent) = (self.scene, self.settings, self.steps_spent);
+        let (origin, ray) = self
+            .camera
+            .ray(screen_x + self.jitter.x, screen_y + self.jitter.y);
+        if let Some(budget) = settings.step_budget
+            && steps_spent.load(Ordering::Relaxed) >= budget
+        {
+            // pixels left over once the budget runs out show the background
+            self.partial.store(true, Ordering::Relaxed);
+            return Vec3::splat(scene.background.sample(ray)).extend(0.0);
+        }
+        if scene.aperture <= 0.0 {
+            let (color, steps) = ray_color(scene, settings, origin, ray);
+            steps_spent.fetch_add(steps as u64, Ordering::Relaxed);
+            return color;
+        }
+        let mut color = Vec4::ZERO;
+        for index in 0..LENS_SAMPLES {
+            let lens = lens_offset(index, scene.aperture);
+            let (origin, ray) = self
+                .camera
+                .lens_ray(origin, ray, lens, scene.focal_distance);
+            let (lens_color, steps) = ray_color(scene, settings, origin, ray);
+            steps_spent.fetch_add(steps as u64, Ordering::Relaxed);
+            color += lens_color;
+        }
+        color / LENS_SAMPLES as f32
+    }
+
+    fn pixel(&self, screen_x: usize, screen_y: usize) -> Vec4 {
+        let (screen_x, screen_y) = (screen_x as f32, screen_y as f32);
+        if self.scene.antialias <= 1 {
+            return self.sample(screen_x, screen_y);
+        }
+        // average a regular grid of sub-rays spread over the character cell
+        let n = self.scene.antialias;
+        let mut color = Vec4::ZERO;
+        for sub_y in 0..n {
+            for sub_x in 0..n {
+                let dx = (sub_x as f32 + 0.5) / n as f32 - 0.5;
+                let dy = (sub_y as f32 + 0.5) / n as f32 - 0.5;
+                color += self.sample(screen_x + dx, screen_y + dy);
+            }
+        }
+        color / (n * n) as f32
+    }
+
+    /// Tone mapped and gamma corrected pixels of the row inside `columns`, left to right.
+    fn pixels(&self, screen_y: usize) -> impl Iterator<Item = Vec4> + '_ {
+        self.columns.clone().map(move |screen_x| {
+            let pixel = self.pixel(screen_x, screen_y);
+            let color = self
+                .scene
+                .tone_map
+                .apply(pixel.truncate().max(Vec3::ZERO))
+                .powf(1.0 / self.scene.gamma);
+            color.extend(pixel.w)
+        })
+    }
+}
+
+/// Renders every row with `row`, in parallel with the `rayon` feature.
+fn map_rows<Row: Send>(
+    rows: impl Iterator<Item = usize>,
+    row: impl Fn(usize) -> Row + Sync + Send,
+) -> Vec<Row> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        rows.collect::<Vec<_>>().into_par_iter().map(row).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        rows.map(row).collect()
+    }
+}
+
+/// Renders square pixels into RGBA8 bytes, row by row. Alpha is 0 where the rays miss the
+/// scene so the image can be composited over something else.
+pub fn render_rgba(
+    scene: &Scene,
+    settings: &RenderSettings,
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    render_rows_with_coverage(
+        scene,
+        settings,
+        width,
+        height,
+        1.0,
+        0..width,
+        0..height,
+        &AtomicU64::new(0),
+        |_, pixels| {
+            pixels
+                .flat_map(|pixel| pixel.to_array().map(to_byte))
+                .collect::<Vec<u8>>()
+        },
+    )
+    .0
+    .concat()
+}
+
+/// Distance along the ray to the first hit for every pixel row by row, `f32::INFINITY` for misses.
+pub fn render_depth(
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+) -> Vec<f32> {
+    let camera = Camera::new(scene, screen_width, screen_height, screen_aspect);
+    map_rows(0..screen_height, |screen_y| {
+        (0..screen_width)
+            .map(|screen_x| {
+                let (origin, ray) = camera.ray(screen_x as f32, screen_y as f32);
+                let hit = cast_ray(&scene.scene, settings, origin, ray);
+                if hit.hit { hit.distance } else { f32::INFINITY }
+            })
+            .collect::<Vec<_>>()
+    })
+    .concat()
+}
+
+/// Renders the scene as rows of symbols.
+/// `screen_aspect` is the width of a character cell divided by its height.
+pub fn render_scene(
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+) -> String {
+    let mut buffer = String::new();
+    render_scene_into(
+        &mut buffer,
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+    );
+    buffer
+}
+
+/// Same as [`render_scene`], but replaces the contents of `buffer` to reuse its allocation.
+/// Returns true when [`RenderSettings::step_budget`] cut the frame short.
+pub fn render_scene_into(
+    buffer: &mut String,
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+) -> bool {
+    render_scene_progressive(
+        buffer,
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+        |_| {},
+    )
+}
+
+/// Same as [`render_scene_into`], but hands every intermediate frame of an
+/// [interlaced](RenderSettings::interlace) render to `on_pass` as soon as it is ready.
+pub fn render_scene_progressive(
+    buffer: &mut String,
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+    on_pass: impl FnMut(&str),
+) -> bool {
+    check_symbols(scene);
+    let max_char_len = scene.symbols.iter().map(|c| c.len_utf8()).max().unwrap();
+    render_text_progressive(
+        buffer,
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+        max_char_len * screen_width,
+        |screen_y, pixels, row| {
+            let mut len = 0;
+            for (screen_x, color) in pixels.enumerate() {
+                let symbol = symbol(scene, luminance(color), screen_x, screen_y);
+                len += symbol.encode_utf8(&mut row[len..]).len();
+            }
+            len
+        },
+        on_pass,
+    )
+}
+
+/// Renders only the characters in columns `x0..x1` and rows `y0..y1` of a
+/// `screen_width` x `screen_height` frame, exactly as they appear in the full [`render_scene`].
+#[allow(clippy::too_many_arguments)]
+pub fn render_region(
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+    scene: &Scene,
+    settings: &RenderSettings,
+    screen_width: usize,
+    screen_height: usize,
+    screen_aspect: f32,
+) -> String {
+    assert!(
+        x0 <= x1 && x1 <= screen_width && y0 <= y1 && y1 <= screen_height,
+        "region must lie inside the frame"
+    );
+    check_symbols(scene);
+    let (rows, _) = render_rows_with_coverage(
+        scene,
+        settings,
+        screen_width,
+        screen_height,
+        screen_aspect,
+        x0..x1,
+        y0..y1,
+        &AtomicU64::new(0),
+        |screen_y, pixels| {
+            pixels
+                .zip(x0..)
+                .map(|(pixel, screen_x)| {
+                    symbol(scene, luminance(pixel.truncate()), screen_x, screen_y)
+                })
+                .collect::<String>()
+        },
+    );
+    let mut buffer = String::new();
+    join_rows_into(&mut buffer, &rows);
+    buffer
+}
+
+pub(crate) fn join_rows_into(buffer: &mut String, rows: &[String]) {
+    buffer.clear();
+    for (index, row) in rows.iter().enumerate() {
+        if index > 0 {
+            buffer.push('\n');
+        }
+        buffer.push_str(row);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Projection {
+    Orthographic { size: f32 },
+    Perspective { fov_y_radians: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShadingMode {
+    Lambert,
+    /// Surface normal mapped to color as `normal * 0.5 + 0.5`.
+    Normals,
+    /// Brightness falling off with the hit distance, black at `RenderSettings::max_distance`.
+    Depth,
+    /// Marching step count relative to `RenderSettings::max_steps`, including rays that miss.
+    StepHeat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ToneMap {
+    /// Intensities are clamped to 1 by the symbol ramp.
+    None,
+    /// `x / (1 + x)`, approaches 1 slowly and never clips.
+    Reinhard,
+    /// Filmic curve fitted to ACES, more contrast than Reinhard and reaches 1 at high intensities.
+    Aces,
+}
+
+impl ToneMap {
+    fn apply(self, color: Vec3) -> Vec3 {
+        match self {
+            ToneMap::None => color,
+            ToneMap::Reinhard => color / (color + 1.0),
+            ToneMap::Aces => {
+                let numerator = color * (color * 2.51 + 0.03);
+                let denominator = color * (color * 2.43 + 0.59) + 0.14;
+                (numerator / denominator).clamp(Vec3::ZERO, Vec3::ONE)
+            }
+        }
+    }
+}
+
+/// How surface normals are estimated from the distance field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalMethod {
+    /// Samples on both sides of the point along every axis, six distance evaluations.
+    Central,
+    /// Samples the point and one side along every axis, four distance evaluations
+    /// but less accurate on curved surfaces.
+    Forward,
+}
+
+/// Intensity seen by rays that miss every surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Background {
+    Solid(f32),
+    /// Blends from `bottom` for rays pointing straight down to `top` for rays pointing straight up.
+    Gradient {
+        top: f32,
+        bottom: f32,
+    },
+}
+
+impl Background {
+    fn sample(&self, ray: Vec3) -> f32 {
+        match *self {
+            Background::Solid(intensity) => intensity,
+            Background::Gradient { top, bottom } => {
+                bottom + (top - bottom) * (ray.y * 0.5 + 0.5).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Light {
+    Directional {
+        /// Direction from the surface towards the light, does not need to be normalized.
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+    },
+    /// Light falling off with the squared distance from `position`.
+    Point {
+        position: Vec3,
+        color: Vec3,
+        intensity: f32,
+    },
+}
+
+impl Light {
+    /// Direction towards the light, the light color reaching `point` and the distance to the light.
+    fn incoming(&self, point: Vec3) -> (Vec3, Vec3, f32) {
+        match *self {
+            Light::Directional {
+                direction,
+                color,
+                intensity,
+            } => (
+                direction.normalize_or_zero(),
+                color * intensity,
+                f32::INFINITY,
+            ),
+            Light::Point {
+                position,
+                color,
+                intensity,
+            } => {
+                let to_light = position - point;
+                let distance_squared = to_light.length_squared();
+                (
+                    to_light.normalize_or_zero(),
+                    color * intensity / distance_squared,
+                    distance_squared.sqrt(),
+                )
+            }
+        }
+    }
+}
+
+pub struct Scene {
+    pub scene: Box<dyn Sdf>,
+    pub camera_pos: Vec3,
+    pub look_at: Vec3,
+    pub camera_up: Vec3,
+    /// Rotation around the view direction, in radians.
+    pub camera_roll: f32,
+    pub projection: Projection,
+    /// Distance from the camera to the plane that stays in focus.
+    pub focal_distance: f32,
+    /// Radius of the lens, surfaces away from the focal plane blur more with a larger one.
+    /// 0 keeps everything sharp.
+    pub aperture: f32,
+    pub lights: Vec<Light>,
+    /// Ambient light reaching surfaces facing straight up.
+    pub ambient_sky: Vec3,
+    /// Ambient light reaching surfaces facing straight down, sideways surfaces get the average.
+    pub ambient_ground: Vec3,
+    /// Scale of the light reaching surfaces directly, added on top of the ambient light.
+    pub diffuse: f32,
+    /// Width of the shadow penumbra, 0 gives hard shadows.
+    pub shadow_softness: f32,
+    pub ao_samples: usize,
+    pub ao_strength: f32,
+    /// Number of rays per character cell along each axis, 1 disables supersampling.
+    pub antialias: usize,
+    /// Animation frame number used to shift all rays by a different sub-character offset
+    /// every frame, so successive frames blend into an antialiased image. `None` disables it.
+    pub jitter_frame: Option<u32>,
+    /// Characters from the darkest to the brightest, must not be empty.
+    pub symbols: Vec<char>,
+    /// Perceived brightness of every symbol, ascending. Each intensity picks the symbol
+    /// with the closest brightness. `None` spreads intensities evenly over the ramp.
+    pub symbol_brightness: Option<Vec<f32>>,
+    /// Ordered dithering between neighbouring symbols to break up banding on smooth gradients.
+    pub dither: bool,
+    /// Output gamma applied to every pixel, 1 leaves intensities linear.
+    pub gamma: f32,
+    /// Compresses intensities above 1 before gamma, so highlights don't clip.
+    pub tone_map: ToneMap,
+    pub shading_mode: ShadingMode,
+    pub shininess: f32,
+    pub specular_strength: f32,
+    /// Falloff of the rim light away from silhouettes, higher values give a thinner rim.
+    pub rim_power: f32,
+    /// Brightness of the rim light, 0 disables it.
+    pub rim_strength: f32,
+    /// Strength of light shining through thin parts of shapes lit from behind,
+    /// 0 keeps everything opaque.
+    pub translucency: f32,
+    /// Exponential fog density, 0 disables fog.
+    pub fog_density: f32,
+    pub fog_color: Vec3,
+    pub background: Background,
+}
+
+impl Scene {
+    /// Scene showing `scene` with the default camera, a single white directional light and
+    /// plain Lambert shading. [`SceneBuilder`](crate::SceneBuilder) and `SceneDesc` start from
+    /// these values too.
+    pub fn new(scene: Box<dyn Sdf>) -> Self {
+        Self {
+            scene,
+            camera_pos: vec3(0.0, 0.0, 20.0),
+            look_at: Vec3::ZERO,
+            camera_up: Vec3::Y,
+            camera_roll: 0.0,
+            projection: Projection::Orthographic { size: 25.0 },
+            focal_distance: 20.0,
+            aperture: 0.0,
+            lights: vec![Light::Directional {
+                direction: vec3(-1.0, 1.0, 1.0),
+                color: Vec3::ONE,
+                intensity: 1.0,
+            }],
+            ambient_sky: Vec3::splat(0.1),
+            ambient_ground: Vec3::splat(0.1),
+            diffuse: 0.9,
+            shadow_softness: 0.1,
+            ao_samples: 5,
+            ao_strength: 1.0,
+            antialias: 1,
+            jitter_frame: None,
+            symbols: DEFAULT_SYMBOLS.chars().collect(),
+            symbol_brightness: None,
+            dither: false,
+            gamma: 1.0,
+            tone_map: ToneMap::None,
+            shading_mode: ShadingMode::Lambert,
+            shininess: 16.0,
+            specular_strength: 0.3,
+            rim_power: 3.0,
+            rim_strength: 0.0,
+            translucency: 0.0,
+            fog_density: 0.0,
+            fog_color: Vec3::ZERO,
+            background: Background::Solid(0.0),
+        }
+    }
+}
+
+pub fn scene(time: f32) -> Scene {
+    Scene::new(
+        SdfTransform {
+            mat: Mat4::from_rotation_x(time) * Mat4::from_rotation_y(time),
+            inner: [
+                SdfSmoothUnion {
+                    a: SdfSphere {
+                        center: Vec3::ZERO,
+                        radius: 7.0,
+                    },
+                    b: SdfDonut {
+                        center: Vec3::ZERO,
+                        radius: 10.0,
+                        tube_radius: 2.0,
+                        axis: Vec3::Z,
+                    },
+                    k: 3.0,
+                }
+                .boxed(),
+                SdfBox {
+                    center: vec3(f32::sin(time * 2.0) * 3.0, 0.0, 0.0),
+                    half_size: vec3(10.0, 3.0, 3.0),
+                }
+                .boxed(),
+                SdfCylinder {
+                    center: Vec3::ZERO,
+                    radius: 2.0,
+                    half_height: 12.0,
+                }
+                .boxed(),
+            ],
+            distance_scale: 1.0,
+        }
+        .boxed(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn capsule_distance() {
+        let capsule = SdfCapsule {
+            a: vec3(-2.0, 1.0, 0.0),
+            b: vec3(4.0, 1.0, 3.0),
+            radius: 1.5,
+        };
+        assert_near(capsule.distance((capsule.a + capsule.b) * 0.5), -1.5);
+        // one radius from `a` towards `b` is still on the axis, the surface is behind `a`
+        let along = (capsule.b - capsule.a).normalize() * capsule.radius;
+        assert_near(capsule.distance(capsule.a + along), -1.5);
+        assert_near(capsule.distance(capsule.a - along), 0.0);
+    }
+
+    #[test]
+    fn plane_distance_is_linear_along_normal() {
+        let plane = SdfPlane {
+            normal: vec3(0.0, 0.6, 0.8),
+            offset: 2.0,
+        };
+        let on_plane = plane.normal * -2.0 + vec3(1.0, 0.8, -0.6) * 5.0;
+        assert_near(plane.distance(on_plane), 0.0);
+        for offset in [-3.0, -0.5, 1.0, 4.0] {
+            assert_near(plane.distance(on_plane + plane.normal * offset), offset);
+        }
+    }
+
+    #[test]
+    fn smooth_union_blends_between_spheres() {
+        let sphere = |x: f32| SdfSphere {
+            center: vec3(x, 0.0, 0.0),
+            radius: 1.0,
+        };
+        let blended = SdfSmoothUnion {
+            a: sphere(-1.5),
+            b: sphere(1.5),
+            k: 1.0,
+        };
+        // both spheres are 0.5 away, h = 0.5 so the blend digs k / 4 below the plain minimum
+        assert_near(blended.distance(Vec3::ZERO), 0.25);
+
+        let hard = SdfSmoothUnion { k: 0.0, ..blended };
+        assert_near(hard.distance(Vec3::ZERO), 0.5);
+        assert_near(hard.distance(vec3(-1.5, 2.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn subtract_carves_b_out_of_a() {
+        let cube = || SdfBox {
+            center: Vec3::ZERO,
+            half_size: Vec3::splat(3.0),
+        };
+        let drill = || SdfCylinder {
+            center: Vec3::ZERO,
+            radius: 1.0,
+            half_height: 5.0,
+        };
+        let inside_both = vec3(0.5, 0.0, 0.0);
+        assert!(cube().distance(inside_both) < 0.0);
+        assert!(drill().distance(inside_both) < 0.0);
+
+        let hole = SdfSubtract {
+            a: cube(),
+            b: drill(),
+        };
+        assert_near(hole.distance(inside_both), 0.5);
+        assert!(hole.distance(vec3(2.0, 0.0, 0.0)) < 0.0);
+
+        let smooth_hole = SdfSmoothSubtract {
+            a: cube(),
+            b: drill(),
+            k: 0.5,
+        };
+        assert!(smooth_hole.distance(inside_both) > 0.0);
+    }
+
+    #[test]
+    fn intersect_keeps_the_lens_between_spheres() {
+        let sphere = |x: f32| SdfSphere {
+            center: vec3(x, 0.0, 0.0),
+            radius: 2.0,
+        };
+        let lens = SdfIntersect {
+            a: sphere(-1.0),
+            b: sphere(1.0),
+        };
+        assert_near(lens.distance(Vec3::ZERO), -1.0);
+        // inside the left sphere only
+        let left_only = vec3(-2.5, 0.0, 0.0);
+        assert!(sphere(-1.0).distance(left_only) < 0.0);
+        assert_near(lens.distance(left_only), 1.5);
+
+        let smooth_lens = SdfSmoothIntersect {
+            a: sphere(-1.0),
+            b: sphere(1.0),
+            k: 0.5,
+        };
+        assert!(smooth_lens.distance(Vec3::ZERO) < 0.0);
+        assert!(smooth_lens.distance(left_only) > 0.0);
+    }
+
+    #[test]
+    fn render_scene_separates_rows_with_newlines() {
+        let frame = render_scene(&scene(0.0), &RenderSettings::default(), 4, 2, 1.0);
+        assert_eq!(frame.matches('\n').count(), 1);
+        assert!(!frame.ends_with('\n'));
+    }
+
+    #[test]
+    fn camera_forward_points_at_look_at() {
+        let scene = Scene {
+            camera_pos: vec3(3.0, 4.0, 20.0),
+            look_at: vec3(3.0, 4.0, 0.0),
+            ..scene(0.0)
+        };
+        let camera = Camera::new(&scene, 8, 8, 1.0);
+        assert!(camera.forward.abs_diff_eq(Vec3::NEG_Z, 1e-6));
+    }
+
+    #[test]
+    fn centered_pixel_casts_from_camera_pos() {
+        let scene = Scene {
+            camera_pos: vec3(5.0, -2.0, 20.0),
+            look_at: vec3(5.0, -2.0, 0.0),
+            ..scene(0.0)
+        };
+        let camera = Camera::new(&scene, 5, 5, 1.0);
+        let (origin, ray) = camera.ray(2.0, 2.0);
+        assert!(origin.abs_diff_eq(scene.camera_pos, 1e-5));
+        assert!(ray.abs_diff_eq(Vec3::NEG_Z, 1e-6));
+    }
+
+    #[test]
+    fn light_direction_points_towards_the_light() {
+        let light = Light::Directional {
+            direction: vec3(0.0, 2.0, 2.0),
+            color: Vec3::ONE,
+            intensity: 1.0,
+        };
+        let (light_dir, _, _) = light.incoming(Vec3::ZERO);
+        assert_near(
+            lambert_shading(Vec3::Y, light_dir),
+            std::f32::consts::FRAC_1_SQRT_2,
+        );
+        assert_near(lambert_shading(Vec3::NEG_Y, light_dir), 0.0);
+    }
+
+    #[test]
+    fn perspective_corner_rays_diverge() {
+        let corner_rays = |projection| {
+            let scene = Scene {
+                projection,
+                ..scene(0.0)
+            };
+            let camera = Camera::new(&scene, 10, 6, 1.0);
+            (camera.ray(0.0, 0.0), camera.ray(9.0, 5.0))
+        };
+
+        let ((_, top_left), (_, bottom_right)) =
+            corner_rays(Projection::Orthographic { size: 25.0 });
+        assert!(top_left.abs_diff_eq(bottom_right, 1e-6));
+
+        let ((top_left_origin, top_left), (bottom_right_origin, bottom_right)) =
+            corner_rays(Projection::Perspective { fov_y_radians: 1.0 });
+        assert_eq!(top_left_origin, bottom_right_origin);
+        assert!(top_left.dot(bottom_right) < 0.9);
+        assert!(top_left.x < 0.0 && top_left.y > 0.0);
+        assert!(bottom_right.x > 0.0 && bottom_right.y < 0.0);
+    }
+
+    /// A sphere floating above the ground plane `y = 0`.
+    fn sphere_over_plane() -> Vec<Box<dyn Sdf>> {
+        vec![
+            SdfPlane {
+                normal: Vec3::Y,
+                offset: 0.0,
+            }
+            .boxed(),
+            SdfSphere {
+                center: vec3(0.0, 3.0, 0.0),
+                radius: 1.0,
+            }
+            .boxed(),
+        ]
+    }
+
+    /// Lit color of an upward facing point on the ground, seen from above.
+    fn ground_color(scene: &Scene, point: Vec3) -> Vec3 {
+        shade(
+            scene,
+            &RenderSettings::default(),
+            point,
+            Vec3::Y,
+            Material::default(),
+            Vec3::NEG_Y,
+        )
+    }
+
+    #[test]
+    fn sphere_shadows_the_plane_below() {
+        let scene = Scene {
+            scene: sphere_over_plane().boxed(),
+            lights: vec![Light::Directional {
+                direction: Vec3::Y,
+                color: Vec3::ONE,
+                intensity: 1.0,
+            }],
+            ao_samples: 0,
+            ..scene(0.0)
+        };
+        let shadowed = ground_color(&scene, Vec3::ZERO);
+        let lit = ground_color(&scene, vec3(10.0, 0.0, 0.0));
+        assert!(shadowed.x < lit.x * 0.5, "{shadowed} vs {lit}");
+    }
+
+    #[test]
+    fn crevices_are_more_occluded_than_bumps() {
+        // narrow slot cut 3 deep into the top of a block
+        let crevice = SdfSubtract {
+            a: SdfBox {
+                center: vec3(0.0, -5.0, 0.0),
+                half_size: Vec3::splat(5.0),
+            },
+            b: SdfBox {
+                center: Vec3::ZERO,
+                half_size: vec3(0.4, 3.0, 5.0),
+            },
+        };
+        let bump = SdfSphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let crevice_bottom = ambient_occlusion(&crevice, vec3(0.0, -3.0, 0.0), Vec3::Y, 5, 1.0);
+        let bump_top = ambient_occlusion(&bump, vec3(0.0, 1.0, 0.0), Vec3::Y, 5, 1.0);
+        assert!(crevice_bottom < bump_top, "{crevice_bottom} vs {bump_top}");
+        assert_near(bump_top, 1.0);
+    }
+
+    #[test]
+    fn demo_frame_matches_reference() {
+        // rendered row by row, the parallel renderer has to produce exactly the same frame
+        let expected = [
+            "                                        ",
+            "                                        ",
+            "                     8@@@@80f           ",
+            "                8@80GL00fLG8@8G;        ",
+            "             8@8@880GCLti;itG@8L:       ",
+            "           @8@@@@@80GCLf18@@@@@@;       ",
+            "         88C8@@@@@@0GCft:@@@@@@...      ",
+            "        @0fG088888GCLftG@@@@@@.....     ",
+            "       @8L.LGGGGCCLft1;....,,......     ",
+            "       @8fiitffftt1ii;LL@@Gf; ,...      ",
+            "       @@Gfiiiit;;1iL@@@Gf;.....,       ",
+            "       G@@@800008@@@@Gf1,..             ",
+            "        1CG0@@C0GCft;,..                ",
+            "           .,,,,...                     ",
+            "                                        ",
+            "                                        ",
+        ]
+        .join("\n");
+        let frame = render_scene(&scene(0.7), &RenderSettings::default(), 40, 16, 0.5);
+        assert_eq!(frame, expected);
+    }
+
+    /// Fully bright shape unaffected by lights, so hits show as the brightest symbol.
+    fn glowing(inner: impl Sdf + 'static) -> Box<dyn Sdf> {
+        SdfMaterial {
+            inner,
+            material: Material {
+                emission: Vec3::ONE,
+                ..Material::default()
+            },
+        }
+        .boxed()
+    }
+
+    #[test]
+    fn antialiasing_softens_edges() {
+        // 5x5 pixels one unit apart, the edge of the box runs just right of the middle column
+        let mut scene = Scene {
+            scene: glowing(SdfBox {
+                center: vec3(-4.9, 0.0, 0.0),
+                half_size: Vec3::splat(5.0),
+            }),
+            projection: Projection::Orthographic { size: 4.0 },
+            ..scene(0.0)
+        };
+        let middle = |scene: &Scene| {
+            let frame = render_scene(scene, &RenderSettings::default(), 5, 5, 1.0);
+            frame.lines().nth(2).unwrap().chars().nth(2).unwrap()
+        };
+        assert_eq!(middle(&scene), '@');
+        scene.antialias = 2;
+        let softened = middle(&scene);
+        assert!(!matches!(softened, ' ' | '@'), "{softened:?}");
+    }
+
+    #[test]
+    fn relaxation_takes_fewer_steps() {
+        // a ray skimming the top of a huge sphere approaches its surface slowly
+        let sphere = SdfSphere {
+            center: vec3(0.0, -100.0, 0.0),
+            radius: 100.0,
+        };
+        let settings = RenderSettings::default();
+        let start = vec3(-10.0, 2.0, 0.0);
+        let ray = vec3(1.0, -0.3, 0.0).normalize();
+        let plain = march(&sphere, &settings, start, ray, 1.0);
+        let relaxed = march(&sphere, &settings, start, ray, RELAXATION);
+        assert!(plain.hit && relaxed.hit);
+        assert!(
+            relaxed.steps < plain.steps,
+            "{} vs {}",
+            relaxed.steps,
+            plain.steps
+        );
+        assert!(sphere.distance(relaxed.position).abs() < settings.epsilon);
+        assert!(relaxed.position.distance(plain.position) < settings.epsilon);
+    }
+
+    #[test]
+    fn donut_axis_orients_the_ring() {
+        let donut = SdfDonut {
+            center: vec3(1.0, 2.0, 3.0),
+            radius: 5.0,
+            tube_radius: 1.0,
+            axis: Vec3::Y,
+        };
+        // the ring lies in the XZ plane around the center
+        for direction in [Vec3::X, Vec3::NEG_X, Vec3::Z, vec3(0.6, 0.0, -0.8)] {
+            assert_near(donut.distance(donut.center + direction * 5.0), -1.0);
+            assert_near(donut.distance(donut.center + direction * 6.0), 0.0);
+        }
+        assert_near(donut.distance(donut.center + vec3(5.0, 1.0, 0.0)), 0.0);
+        assert!(donut.distance(donut.center + Vec3::Y * 5.0) > 0.0);
+    }
+
+    #[test]
+    fn repeat_tiles_space() {
+        let lattice = SdfRepeat {
+            inner: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 0.5,
+            },
+            period: vec3(3.0, 4.0, 0.0),
+        };
+        let pt = vec3(0.7, -1.1, 0.3);
+        for shift in [
+            vec3(3.0, 0.0, 0.0),
+            vec3(-6.0, 4.0, 0.0),
+            vec3(0.0, -8.0, 0.0),
+        ] {
+            assert_near(lattice.distance(pt + shift), lattice.distance(pt));
+        }
+        // no repetition along Z
+        assert_near(lattice.distance(vec3(0.0, 0.0, 10.0)), 9.5);
+    }
+
+    #[test]
+    fn limited_repeat_stops_at_the_grid_edge() {
+        let sphere = || SdfSphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let grid = SdfRepeatLimited {
+            inner: sphere(),
+            period: Vec3::splat(4.0),
+            count: IVec3::new(3, 2, -1),
+        };
+        // copies sit at x = 0, 4, 8 and y = 0, 4, only the one at z = 0
+        assert_near(grid.distance(vec3(4.0, 4.0, 0.0)), -1.0);
+        assert_near(grid.distance(vec3(20.0, 0.0, 0.0)), 11.0);
+        assert_near(grid.distance(vec3(-5.0, 0.0, 0.0)), 4.0);
+        assert_near(grid.distance(vec3(8.0, 10.0, 0.0)), 5.0);
+        assert_near(grid.distance(vec3(0.0, 0.0, 4.0)), 3.0);
+    }
+
+    #[test]
+    fn zero_twist_is_the_identity() {
+        let cube = || SdfBox {
+            center: vec3(1.0, 0.0, 0.0),
+            half_size: vec3(2.0, 3.0, 1.0),
+        };
+        let twisted = SdfTwist {
+            inner: cube(),
+            amount: 0.0,
+        };
+        for pt in [
+            vec3(0.0, 0.0, 0.0),
+            vec3(3.0, 2.0, -1.0),
+            vec3(-4.0, 5.0, 2.5),
+        ] {
+            assert_eq!(twisted.distance(pt), cube().distance(pt));
+        }
+    }
+
+    #[test]
+    fn round_grows_faces_and_rounds_corners() {
+        let half_size = vec3(2.0, 1.0, 3.0);
+        let rounded = SdfRound {
+            inner: SdfBox {
+                center: Vec3::ZERO,
+                half_size,
+            },
+            radius: 0.5,
+        };
+        let grown = SdfBox {
+            center: Vec3::ZERO,
+            half_size: half_size + 0.5,
+        };
+        for pt in [
+            vec3(4.0, 0.0, 0.0),
+            vec3(0.0, -3.0, 0.0),
+            vec3(0.0, 0.0, 5.0),
+        ] {
+            assert_near(rounded.distance(pt), grown.distance(pt));
+        }
+        let corner = half_size + 1.0;
+        assert!(rounded.distance(corner) > grown.distance(corner) + 0.1);
+    }
+
+    #[test]
+    fn shell_has_inner_and_outer_walls() {
+        let shell = SdfShell {
+            inner: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 3.0,
+            },
+            thickness: 0.25,
+        };
+        assert_near(shell.distance(vec3(3.25, 0.0, 0.0)), 0.0);
+        assert_near(shell.distance(vec3(0.0, -2.75, 0.0)), 0.0);
+        assert_near(shell.distance(vec3(0.0, 0.0, 3.0)), -0.25);
+        // the cavity is empty space
+        assert_near(shell.distance(vec3(1.0, 0.0, 0.0)), 1.75);
+    }
+
+    #[test]
+    fn inverted_ramp_maps_full_intensity_to_the_first_default_symbol() {
+        // for dark text on a light background
+        let scene = Scene {
+            symbols: DEFAULT_SYMBOLS.chars().rev().collect(),
+            ..scene(0.0)
+        };
+        assert_eq!(symbol(&scene, 1.0, 0, 0), ' ');
+        assert_eq!(symbol(&scene, 0.0, 0, 0), '@');
+    }
+
+    #[test]
+    fn cone_apex_and_base() {
+        let cone = SdfCone {
+            center: vec3(1.0, 2.0, 0.0),
+            angle: 0.5,
+            height: 3.0,
+        };
+        assert_near(cone.distance(cone.center), 0.0);
+        // straight below the base on the axis the flat cap is the closest feature
+        assert_near(cone.distance(cone.center - vec3(0.0, 4.5, 0.0)), 1.5);
+        assert!(cone.distance(cone.center - vec3(0.0, 2.0, 0.0)) < 0.0);
+    }
+
+    #[test]
+    fn ellipsoid_with_equal_radii_is_a_sphere() {
+        let center = vec3(1.0, -2.0, 3.0);
+        let ellipsoid = SdfEllipsoid {
+            center,
+            radii: Vec3::splat(2.0),
+        };
+        let sphere = SdfSphere {
+            center,
+            radius: 2.0,
+        };
+        for offset in [
+            vec3(0.5, 0.0, 0.0),
+            vec3(1.0, 1.0, -1.0),
+            vec3(0.0, 2.0, 0.0),
+            vec3(3.0, -4.0, 2.0),
+        ] {
+            let pt = center + offset;
+            assert_near(ellipsoid.distance(pt), sphere.distance(pt));
+        }
+    }
+
+    #[test]
+    fn octahedron_vertices_and_center() {
+        let octahedron = SdfOctahedron {
+            center: vec3(2.0, 0.0, -1.0),
+            size: 3.0,
+        };
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            assert_near(octahedron.distance(octahedron.center + axis * 3.0), 0.0);
+            assert_near(octahedron.distance(octahedron.center - axis * 3.0), 0.0);
+        }
+        assert_near(octahedron.distance(octahedron.center), -3.0 / 3f32.sqrt());
+
+        // every face points away from the center along its diagonal
+        let settings = RenderSettings::default();
+        for x in [-1.0, 1.0] {
+            for y in [-1.0, 1.0] {
+                for z in [-1.0, 1.0] {
+                    let diagonal = vec3(x, y, z);
+                    let face_center = octahedron.center + diagonal;
+                    let normal = estimate_normal(&octahedron, &settings, face_center);
+                    assert!(normal.abs_diff_eq(diagonal.normalize(), 1e-3), "{normal}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mirror_makes_symmetric_lobes() {
+        let lobes = SdfMirror {
+            inner: SdfSphere {
+                center: vec3(3.0, 0.0, 0.0),
+                radius: 1.0,
+            },
+            axis: Vec3::X,
+        };
+        for pt in [
+            vec3(3.0, 0.0, 0.0),
+            vec3(3.5, 2.0, -1.0),
+            vec3(0.5, 0.0, 0.0),
+        ] {
+            let reflected = pt * vec3(-1.0, 1.0, 1.0);
+            assert_near(lobes.distance(reflected), lobes.distance(pt));
+        }
+        assert_near(lobes.distance(vec3(-3.0, 0.0, 0.0)), -1.0);
+    }
+
+    #[test]
+    fn nearer_sphere_has_smaller_depth() {
+        let center_depth = |z: f32| {
+            let scene = Scene {
+                scene: SdfSphere {
+                    center: vec3(0.0, 0.0, z),
+                    radius: 2.0,
+                }
+                .boxed(),
+                ..scene(0.0)
+            };
+            let depth = render_depth(&scene, &RenderSettings::default(), 5, 5, 1.0);
+            assert!(depth[0].is_infinite());
+            depth[2 * 5 + 2]
+        };
+        let near = center_depth(5.0);
+        let far = center_depth(-5.0);
+        assert_near(near, 13.0);
+        assert!(near < far, "{near} vs {far}");
+    }
+
+    #[test]
+    fn normals_mode_colors_the_facing_pixel_blue() {
+        let scene = Scene {
+            scene: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 3.0,
+            }
+            .boxed(),
+            shading_mode: ShadingMode::Normals,
+            ..scene(0.0)
+        };
+        let (color, _) = ray_color(
+            &scene,
+            &RenderSettings::default(),
+            vec3(0.0, 0.0, 20.0),
+            Vec3::NEG_Z,
+        );
+        assert!(
+            color.abs_diff_eq(Vec4::new(0.5, 0.5, 1.0, 1.0), 1e-3),
+            "{color}"
+        );
+    }
+
+    #[test]
+    fn specular_peaks_at_the_mirror_direction() {
+        let light_dir = vec3(1.0, 1.0, 0.0).normalize();
+        // rays coming in at angles across the upward facing surface, the mirror one at 45 degrees
+        let highlight = |angle: f32| {
+            let (sin, cos) = angle.sin_cos();
+            phong_specular(Vec3::Y, light_dir, vec3(sin, -cos, 0.0), 16.0)
+        };
+        let peak = highlight(std::f32::consts::FRAC_PI_4);
+        assert_near(peak, 1.0);
+        for angle in [-0.6, 0.0, 0.5, 0.9, 1.2] {
+            assert!(highlight(angle) < peak, "{angle}");
+        }
+    }
+
+    #[test]
+    fn surface_facing_neither_light_keeps_the_ambient_floor() {
+        let side_light = |x: f32| Light::Directional {
+            direction: vec3(x, -0.2, 0.0),
+            color: Vec3::ONE,
+            intensity: 1.0,
+        };
+        let scene = Scene {
+            scene: sphere_over_plane().boxed(),
+            lights: vec![side_light(1.0), side_light(-1.0)],
+            ..scene(0.0)
+        };
+        let color = ground_color(&scene, vec3(10.0, 0.0, 0.0));
+        assert!(color.abs_diff_eq(Vec3::splat(0.1), 1e-3), "{color}");
+    }
+
+    #[test]
+    fn point_light_falls_off_with_squared_distance() {
+        let lit = |height: f32| {
+            let scene = Scene {
+                scene: SdfPlane {
+                    normal: Vec3::Y,
+                    offset: 0.0,
+                }
+                .boxed(),
+                lights: vec![Light::Point {
+                    position: vec3(0.0, height, 0.0),
+                    color: Vec3::ONE,
+                    intensity: 10.0,
+                }],
+                ao_samples: 0,
+                ..scene(0.0)
+            };
+            // without the ambient term only the light is left
+            ground_color(&scene, Vec3::ZERO).x - 0.1
+        };
+        let near = lit(2.0);
+        let far = lit(4.0);
+        assert!(near > 0.0);
+        assert_near(far / near, 0.25);
+    }
+
+    #[test]
+    fn fog_pulls_far_hits_towards_the_fog_color() {
+        let intensity = |z: f32, fog_density: f32| {
+            let scene = Scene {
+                scene: glowing(SdfSphere {
+                    center: vec3(0.0, 0.0, z),
+                    radius: 1.0,
+                }),
+                fog_density,
+                fog_color: Vec3::splat(0.2),
+                ..scene(0.0)
+            };
+            let settings = RenderSettings::default();
+            ray_color(&scene, &settings, vec3(0.0, 0.0, 20.0), Vec3::NEG_Z)
+                .0
+                .x
+        };
+        assert_near(intensity(-40.0, 0.0), 1.0);
+        let mut previous = 1.0;
+        for z in [15.0, 5.0, -10.0, -30.0, -50.0] {
+            let fogged = intensity(z, 0.05);
+            assert!(fogged < previous && fogged > 0.2, "{fogged} at {z}");
+            previous = fogged;
+        }
+    }
+
+    #[test]
+    fn tri_prism_caps_and_interior() {
+        let prism = SdfTriPrism {
+            center: vec3(1.0, 2.0, 3.0),
+            size: Vec2::new(2.0, 4.0),
+        };
+        // the triangle is centered on its centroid
+        assert_near(prism.distance(prism.center + vec3(0.0, 0.0, 4.0)), 0.0);
+        assert_near(prism.distance(prism.center - vec3(0.0, 0.0, 4.0)), 0.0);
+        // the closest rectangular face is one inradius away
+        assert_near(prism.distance(prism.center), -2.0 / 3f32.sqrt());
+        assert!(prism.distance(prism.center + vec3(0.3, 0.5, -3.5)) < 0.0);
+        // middle of the bottom face
+        assert_near(
+            prism.distance(prism.center - vec3(0.0, 2.0 / 3f32.sqrt(), 0.0)),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn hex_prism_flats_and_corners() {
+        let prism = SdfHexPrism {
+            center: vec3(-1.0, 0.5, 2.0),
+            radius: 2.0,
+            half_height: 3.0,
+        };
+        let corner_radius = 2.0 * 2.0 / 3f32.sqrt();
+        for side in 0..6 {
+            // flats face +Z first, corners sit half way between them
+            let flat = std::f32::consts::FRAC_PI_2 + side as f32 * std::f32::consts::FRAC_PI_3;
+            let corner = flat + std::f32::consts::FRAC_PI_6;
+            let around = |angle: f32, length: f32| {
+                prism.center + vec3(angle.cos(), 0.0, angle.sin()) * length
+            };
+            assert_near(prism.distance(around(flat, 2.0)), 0.0);
+            assert_near(prism.distance(around(corner, corner_radius)), 0.0);
+        }
+        assert_near(prism.distance(prism.center), -2.0);
+    }
+
+    #[test]
+    fn rounded_box_keeps_its_outer_faces() {
+        let half_size = vec3(3.0, 2.0, 1.5);
+        let rounded = SdfRoundedBox {
+            center: Vec3::ZERO,
+            half_size,
+            radius: 0.5,
+        };
+        let plain = SdfBox {
+            center: Vec3::ZERO,
+            half_size,
+        };
+        for face in [Vec3::X, Vec3::NEG_Y, Vec3::Z] {
+            for offset in [0.0, 0.7, -0.3] {
+                let pt = face * (half_size + offset);
+                assert_near(rounded.distance(pt), plain.distance(pt));
+            }
+        }
+        // the corners are cut off
+        assert!(rounded.distance(half_size) > 0.0);
+    }
+
+    #[test]
+    fn quarter_roll_swaps_screen_axes() {
+        let bar = |half_size: Vec3, camera_roll: f32| {
+            let scene = Scene {
+                scene: glowing(SdfBox {
+                    center: Vec3::ZERO,
+                    half_size,
+                }),
+                projection: Projection::Orthographic { size: 9.0 },
+                camera_roll,
+                ..scene(0.0)
+            };
+            render_scene(&scene, &RenderSettings::default(), 9, 9, 1.0)
+        };
+        let horizontal = bar(vec3(4.0, 1.0, 1.0), 0.0);
+        let vertical = bar(vec3(1.0, 4.0, 1.0), 0.0);
+        assert_ne!(horizontal, vertical);
+        assert_eq!(
+            bar(vec3(4.0, 1.0, 1.0), std::f32::consts::FRAC_PI_2),
+            vertical
+        );
+        assert_eq!(
+            bar(vec3(1.0, 4.0, 1.0), std::f32::consts::FRAC_PI_2),
+            horizontal
+        );
+    }
+
+    #[test]
+    fn basis_stays_orthonormal_looking_along_up() {
+        for look_at in [vec3(0.0, 10.0, 0.0), vec3(0.0, -10.0, 0.0)] {
+            let (right, up, forward) = camera_basis(Vec3::ZERO, look_at, Vec3::Y);
+            assert!(forward.abs_diff_eq(look_at.normalize(), 1e-6));
+            for axis in [right, up, forward] {
+                assert_near(axis.length(), 1.0);
+            }
+            assert_near(right.dot(up), 0.0);
+            assert_near(right.dot(forward), 0.0);
+            assert_near(up.dot(forward), 0.0);
+        }
+    }
+
+    #[test]
+    fn pixel_radius_ends_far_hits_sooner() {
+        let sphere = SdfSphere {
+            center: vec3(0.0, -100.0, 0.0),
+            radius: 100.0,
+        };
+        let fixed = RenderSettings::default();
+        let scaled = RenderSettings {
+            pixel_radius: 0.005,
+            ..fixed
+        };
+
+        // skimming the sphere from far away
+        let start = vec3(-60.0, 3.0, 0.0);
+        let ray = vec3(1.0, -0.08, 0.0).normalize();
+        let far_fixed = cast_ray(&sphere, &fixed, start, ray);
+        let far_scaled = cast_ray(&sphere, &scaled, start, ray);
+        assert!(far_fixed.hit && far_scaled.hit);
+        assert!(
+            far_scaled.steps < far_fixed.steps,
+            "{} vs {}",
+            far_scaled.steps,
+            far_fixed.steps
+        );
+
+        let near = cast_ray(&sphere, &scaled, vec3(0.0, 1.0, 0.0), Vec3::NEG_Y);
+        assert!(near.hit);
+        assert!(sphere.distance(near.position).abs() < fixed.epsilon);
+    }
+
+    #[test]
+    fn elongated_sphere_keeps_its_radius_along_the_segment() {
+        let stretched = SdfElongate {
+            inner: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 2.0,
+            },
+            amount: vec3(5.0, 0.0, 0.0),
+        };
+        for x in [-5.0, -3.5, 0.0, 1.0, 5.0] {
+            assert_near(stretched.distance(vec3(x, 0.0, 0.0)), -2.0);
+            assert_near(stretched.distance(vec3(x, 2.0, 0.0)), 0.0);
+        }
+        // the caps stay round past the ends
+        assert_near(stretched.distance(vec3(8.0, 0.0, 0.0)), 1.0);
+    }
+
+    /// Passes through to `inner`, counting the distance evaluations.
+    struct Counting<Inner> {
+        inner: Inner,
+        calls: AtomicU64,
+        batches: AtomicU64,
+    }
+
+    impl<Inner> Counting<Inner> {
+        fn new(inner: Inner) -> Self {
+            Self {
+                inner,
+                calls: AtomicU64::new(0),
+                batches: AtomicU64::new(0),
+            }
+        }
+
+        fn calls(&self) -> u64 {
+            self.calls.load(Ordering::Relaxed)
+        }
+
+        fn batches(&self) -> u64 {
+            self.batches.load(Ordering::Relaxed)
+        }
+    }
+
+    impl<Inner: Sdf> Sdf for Counting<Inner> {
+        fn distance(&self, pt: Vec3) -> f32 {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.inner.distance(pt)
+        }
+
+        fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+            self.calls.fetch_add(points.len() as u64, Ordering::Relaxed);
+            self.batches.fetch_add(1, Ordering::Relaxed);
+            self.inner.distance_batch(points, distances);
+        }
+    }
+
+    #[test]
+    fn bounded_skips_the_inner_shape_far_away() {
+        let bounded = SdfBounded {
+            inner: Counting::new(SdfSphere {
+                center: vec3(1.0, 0.0, 0.0),
+                radius: 1.0,
+            }),
+            center: vec3(1.0, 0.0, 0.0),
+            radius: 1.5,
+        };
+        let far = bounded.distance(vec3(30.0, 0.0, 0.0));
+        assert!(far > 0.0 && far <= 28.0, "{far}");
+        assert_eq!(bounded.inner.calls(), 0);
+
+        assert_near(bounded.distance(vec3(2.5, 0.0, 0.0)), 0.5);
+        assert_eq!(bounded.inner.calls(), 1);
+    }
+
+    #[test]
+    fn reflective_plane_mirrors_the_sphere() {
+        let with_floor = |reflectivity: f32| Scene {
+            scene: vec![
+                SdfMaterial {
+                    inner: SdfPlane {
+                        normal: Vec3::Y,
+                        offset: 0.0,
+                    },
+                    material: Material {
+                        reflectivity,
+                        ..Material::default()
+                    },
+                }
+                .boxed(),
+                SdfSphere {
+                    center: vec3(0.0, 3.0, 0.0),
+                    radius: 1.0,
+                }
+                .boxed(),
+            ]
+            .boxed(),
+            lights: vec![Light::Directional {
+                direction: vec3(1.0, 0.1, 0.0),
+                color: Vec3::ONE,
+                intensity: 1.0,
+            }],
+            ..scene(0.0)
+        };
+        let settings = RenderSettings::default();
+        // a ray bouncing off the floor at `point` heads straight for the sphere
+        let point = vec3(2.0, 0.0, 0.0);
+        let bounced = (vec3(0.0, 3.0, 0.0) - point).normalize();
+        let incoming = bounced * vec3(1.0, -1.0, 1.0);
+        let color = |scene: &Scene, start: Vec3, ray: Vec3| {
+            let (color, _) = ray_color(scene, &settings, start, ray);
+            assert_eq!(color.w, 1.0);
+            color.truncate()
+        };
+
+        let matte = with_floor(0.0);
+        let floor = color(&matte, point - incoming * 3.0, incoming);
+        // start off the floor, or the ray would stop on it right away
+        let sphere = color(&matte, point + bounced * 0.5, bounced);
+        assert!(
+            (floor - sphere).abs().max_element() > 0.1,
+            "{floor} vs {sphere}"
+        );
+
+        let mirror = with_floor(0.5);
+        let reflected = color(&mirror, point - incoming * 3.0, incoming);
+        assert!(
+            reflected.abs_diff_eq(floor.lerp(sphere, 0.5), 1e-2),
+            "{reflected} vs {floor} and {sphere}"
+        );
+    }
+
+    #[test]
+    fn displacement_moves_the_surface() {
+        let bumpy = |amplitude: f32| SdfDisplace {
+            inner: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 3.0,
+            },
+            amplitude,
+            frequency: 1.0,
+        };
+        let points = [
+            vec3(3.0, 0.0, 0.0),
+            Vec3::splat(3f32.sqrt()),
+            vec3(-1.0, 4.0, 2.5),
+            vec3(0.3, 0.2, -0.1),
+        ];
+        for pt in points {
+            assert_near(bumpy(0.0).distance(pt), bumpy(0.0).inner.distance(pt));
+        }
+        // the pattern is zero on the axes, elsewhere the surface is pushed out
+        assert_near(bumpy(0.2).distance(points[0]), 0.0);
+        assert!(bumpy(0.2).distance(points[1]) < -0.05);
+    }
+
+    #[test]
+    fn ray_hit_lands_on_the_sphere() {
+        let sphere = SdfSphere {
+            center: vec3(1.0, 2.0, -3.0),
+            radius: 2.0,
+        };
+        let settings = RenderSettings::default();
+        let start = vec3(-6.0, 5.0, 4.0);
+        let hit = cast_ray(
+            &sphere,
+            &settings,
+            start,
+            (sphere.center - start).normalize(),
+        );
+        assert!(hit.hit);
+        assert!(sphere.distance(hit.position).abs() < settings.epsilon);
+        assert!(hit.steps > 0);
+        assert_near(hit.distance, hit.position.distance(start));
+        let outward = (hit.position - sphere.center).normalize();
+        assert!(hit.normal.abs_diff_eq(outward, 1e-2), "{}", hit.normal);
+    }
+
+    #[test]
+    fn grazing_the_limb_takes_more_steps() {
+        let scene = Scene {
+            scene: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 5.0,
+            }
+            .boxed(),
+            shading_mode: ShadingMode::StepHeat,
+            ..scene(0.0)
+        };
+        let settings = RenderSettings::default();
+        let heat = |y: f32| {
+            let (color, _) = ray_color(&scene, &settings, vec3(0.0, y, 20.0), Vec3::NEG_Z);
+            assert_eq!(color.w, 1.0);
+            color.x
+        };
+        let head_on = heat(0.0);
+        let grazing = heat(4.98);
+        assert!(grazing > head_on, "{grazing} vs {head_on}");
+    }
+
+    #[test]
+    fn gamma_brightens_midtones() {
+        let mut scene = Scene {
+            scene: SdfMaterial {
+                inner: SdfSphere {
+                    center: Vec3::ZERO,
+                    radius: 5.0,
+                },
+                material: Material {
+                    emission: Vec3::splat(0.3),
+                    ..Material::default()
+                },
+            }
+            .boxed(),
+            ..scene(0.0)
+        };
+        let ramp_index = |scene: &Scene| {
+            let frame = render_scene(scene, &RenderSettings::default(), 3, 3, 1.0);
+            let middle = frame.lines().nth(1).unwrap().chars().nth(1).unwrap();
+            DEFAULT_SYMBOLS.find(middle).unwrap()
+        };
+        let linear = ramp_index(&scene);
+        scene.gamma = 2.2;
+        let corrected = ramp_index(&scene);
+        assert!(corrected > linear, "{corrected} vs {linear}");
+    }
+
+    #[test]
+    fn translate_moves_the_center() {
+        let moved = SdfTransform::translate(
+            SdfSphere {
+                center: Vec3::ZERO,
+                radius: 1.0,
+            },
+            vec3(3.0, 0.0, 0.0),
+        );
+        assert_near(moved.distance(vec3(3.0, 0.0, 0.0)), -1.0);
+        assert_near(moved.distance(vec3(4.0, 0.0, 0.0)), 0.0);
+        assert_near(moved.distance(Vec3::ZERO), 2.0);
+
+        let turned = SdfTransform::rotate_y(
+            SdfSphere {
+                center: vec3(2.0, 0.0, 0.0),
+                radius: 1.0,
+            },
+            std::f32::consts::FRAC_PI_2,
+        );
+        // +X turns to -Z around Y
+        assert_near(turned.distance(vec3(0.0, 0.0, -2.0)), -1.0);
+    }
+
+    #[test]
+    fn scaled_unit_sphere_matches_a_bigger_sphere() {
+        let unit = || SdfSphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let scaled = SdfScale {
+            inner: unit(),
+            factor: 3.0,
+        };
+        // scaling only the sample point leaves distances in the unit sphere's units
+        let naive = SdfTransform {
+            mat: Mat4::from_scale(Vec3::splat(1.0 / 3.0)),
+            inner: unit(),
+            distance_scale: 1.0,
+        };
+        let big = SdfSphere {
+            center: Vec3::ZERO,
+            radius: 3.0,
+        };
+        for pt in [Vec3::ZERO, vec3(6.0, 0.0, 0.0), vec3(1.0, -2.0, 4.0)] {
+            assert_near(scaled.distance(pt), big.distance(pt));
+        }
+        assert!((naive.distance(vec3(6.0, 0.0, 0.0)) - 3.0).abs() > 1.0);
+    }
+
+    #[test]
+    fn rendering_into_a_reused_buffer_matches_fresh_renders() {
+        let settings = RenderSettings::default();
+        let mut buffer = String::from("left over from before");
+        let mut allocation = None;
+        for time in [0.3, 1.1, 1.7] {
+            let scene = scene(time);
+            render_scene_into(&mut buffer, &scene, &settings, 30, 12, 0.5);
+            assert_eq!(buffer, render_scene(&scene, &settings, 30, 12, 0.5));
+            // only the first frame grows the buffer
+            let current = (buffer.as_ptr(), buffer.capacity());
+            assert_eq!(*allocation.get_or_insert(current), current);
+        }
+    }
+
+    #[test]
+    fn multi_byte_symbols_fill_every_row() {
+        let scene = Scene {
+            symbols: " ░▒▓█".chars().collect(),
+            ..scene(0.4)
+        };
+        let settings = RenderSettings {
+            interlace: true,
+            ..RenderSettings::default()
+        };
+        let frame = render_scene(&scene, &settings, 30, 12, 0.5);
+        assert_eq!(frame.lines().count(), 12);
+        assert!(frame.lines().all(|line| line.chars().count() == 30));
+        assert!(frame.contains('█'));
+    }
+
+    #[test]
+    fn segment_distance_at_the_perpendicular_foot() {
+        let segment = SdfSegment {
+            a: vec3(-3.0, 0.0, 1.0),
+            b: vec3(5.0, 0.0, 1.0),
+            radius: 0.25,
+        };
+        // 2 away from the line, straight above a point between the ends
+        let pt = vec3(1.5, 2.0, 1.0);
+        assert_near(segment.distance(pt), 2.0 - 0.25);
+        // past the ends the round caps take over
+        assert_near(segment.distance(vec3(8.0, 4.0, 1.0)), 5.0 - 0.25);
+
+        let edges = wireframe_box(Vec3::ZERO, Vec3::ONE, 0.1);
+        assert_eq!(edges.len(), 12);
+        assert_near(edges.distance(vec3(1.0, 0.0, 1.0)), -0.1);
+        assert!(edges.distance(Vec3::ZERO) > 0.5);
+    }
+
+    #[test]
+    fn jittered_frames_move_ray_origins_within_the_pixel() {
+        let camera = Camera::new(&scene(0.0), 20, 10, 0.5);
+        let origin = |jitter_frame| {
+            let jitter = frame_jitter(jitter_frame);
+            camera.ray(7.0 + jitter.x, 4.0 + jitter.y).0
+        };
+        assert_eq!(frame_jitter(None), Vec2::ZERO);
+        let still = origin(None);
+        // at most half a pixel away along each axis
+        let reach = Vec2::new(camera.width / 19.0, camera.height / 9.0).length() * 0.5;
+        let origins: Vec<Vec3> = (0..4).map(|frame| origin(Some(frame))).collect();
+        for (frame, jittered) in origins.iter().enumerate() {
+            assert!(jittered.distance(still) <= reach, "frame {frame}");
+            if frame > 0 {
+                assert!(
+                    jittered.distance(origins[frame - 1]) > 1e-3,
+                    "frame {frame}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cell_aspect_changes_the_view_extent() {
+        let scene = scene(0.0);
+        let square = Camera::new(&scene, 40, 20, 1.0);
+        let tall = Camera::new(&scene, 40, 20, 0.5);
+        // twice as many columns as rows, the shorter side always shows 25 units
+        assert_near(square.height, 25.0);
+        assert_near(square.width, 50.0);
+        assert_near(tall.height, 25.0);
+        assert_near(tall.width, 25.0);
+
+        let narrow = Camera::new(&scene, 10, 20, 0.5);
+        assert_near(narrow.width, 25.0);
+        assert_near(narrow.height, 100.0);
+    }
+
+    #[test]
+    fn gradient_background_goes_from_bottom_to_top() {
+        let sky = Background::Gradient {
+            top: 0.8,
+            bottom: 0.2,
+        };
+        assert_near(sky.sample(Vec3::Y), 0.8);
+        assert_near(sky.sample(Vec3::NEG_Y), 0.2);
+        assert_near(sky.sample(Vec3::NEG_Z), 0.5);
+        assert_near(Background::Solid(0.3).sample(Vec3::Y), 0.3);
+
+        // a ray missing everything shows the background
+        let scene = Scene {
+            background: sky,
+            ..scene(0.0)
+        };
+        let start = vec3(0.0, 30.0, 0.0);
+        let (color, _) = ray_color(&scene, &RenderSettings::default(), start, Vec3::Y);
+        assert_eq!(color, Vec4::new(0.8, 0.8, 0.8, 0.0));
+    }
+
+    #[test]
+    fn pyramid_apex_and_base_corners() {
+        let pyramid = SdfPyramid {
+            center: vec3(1.0, -1.0, 2.0),
+            base: 4.0,
+            height: 3.0,
+        };
+        assert_near(pyramid.distance(pyramid.center + vec3(0.0, 3.0, 0.0)), 0.0);
+        for (x, z) in [(-2.0, -2.0), (-2.0, 2.0), (2.0, -2.0), (2.0, 2.0)] {
+            assert_near(pyramid.distance(pyramid.center + vec3(x, 0.0, z)), 0.0);
+        }
+        assert!(pyramid.distance(pyramid.center + vec3(0.0, 1.0, 0.0)) < 0.0);
+
+        // every side face leans outwards and up
+        let settings = RenderSettings::default();
+        for side in [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::NEG_Z] {
+            let face = pyramid.center + side + vec3(0.0, 1.5, 0.0);
+            let normal = estimate_normal(&pyramid, &settings, face);
+            let expected = (side * 3.0 + vec3(0.0, 2.0, 0.0)).normalize();
+            assert!(normal.abs_diff_eq(expected, 1e-2), "{normal} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn calibrated_mapping_is_monotonic_over_the_whole_ramp() {
+        let scene = Scene {
+            symbol_brightness: Some(DEFAULT_SYMBOL_BRIGHTNESS.to_vec()),
+            ..scene(0.0)
+        };
+        let indices: Vec<usize> = (0..=200)
+            .map(|i| {
+                let symbol = symbol(&scene, i as f32 / 200.0, 0, 0);
+                DEFAULT_SYMBOLS.find(symbol).unwrap()
+            })
+            .collect();
+        assert!(indices.windows(2).all(|pair| pair[0] <= pair[1]));
+        let mut used = indices.clone();
+        used.dedup();
+        assert_eq!(used, (0..DEFAULT_SYMBOLS.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn xor_carves_out_the_overlap() {
+        let sphere = |x: f32| SdfSphere {
+            center: vec3(x, 0.0, 0.0),
+            radius: 2.0,
+        };
+        let rings = sphere(-1.0).xor(sphere(1.0));
+        assert!(rings.distance(Vec3::ZERO) > 0.0);
+        assert!(rings.distance(vec3(-2.5, 0.0, 0.0)) < 0.0);
+        assert!(rings.distance(vec3(2.5, 0.0, 0.0)) < 0.0);
+        assert!(rings.distance(vec3(5.0, 0.0, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn near_plane_clips_geometry_in_front_of_it() {
+        let scene = Scene {
+            scene: vec![
+                glowing(SdfSphere {
+                    center: vec3(0.0, 0.0, 15.0),
+                    radius: 1.0,
+                }),
+                SdfSphere {
+                    center: Vec3::ZERO,
+                    radius: 1.0,
+                }
+                .boxed(),
+            ]
+            .boxed(),
+            ..scene(0.0)
+        };
+        let clipped = RenderSettings {
+            near: 10.0,
+            ..RenderSettings::default()
+        };
+        let start = vec3(0.0, 0.0, 20.0);
+        let (near_color, _) = ray_color(&scene, &RenderSettings::default(), start, Vec3::NEG_Z);
+        assert_eq!(near_color, Vec4::ONE);
+        let hit = cast_ray(&scene.scene, &clipped, start, Vec3::NEG_Z);
+        assert!(hit.hit);
+        assert_near(hit.distance, 19.0);
+        let (clipped_color, _) = ray_color(&scene, &clipped, start, Vec3::NEG_Z);
+        assert!(clipped_color.x < 1.0);
+    }
+
+    #[test]
+    fn clip_plane_caps_the_cut_with_a_solid_disc() {
+        let interior = Material {
+            color: vec3(1.0, 0.0, 0.0),
+            ..Material::default()
+        };
+        let cut = SdfClipPlane {
+            inner: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 5.0,
+            },
+            normal: Vec3::Z,
+            offset: 0.0,
+            interior,
+        };
+        let start = vec3(2.0, 1.0, 20.0);
+        let hit = cast_ray(&cut, &RenderSettings::default(), start, Vec3::NEG_Z);
+        // the ray stops on the cut face instead of going on to the back of the sphere
+        assert!(hit.hit);
+        assert!(hit.position.z.abs() < 0.01, "{}", hit.position);
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-3), "{}", hit.normal);
+        assert_eq!(cut.distance_material(hit.position).1, interior);
+    }
+
+    #[test]
+    fn capped_torus_fills_only_its_arc() {
+        let arc = SdfCappedTorus {
+            center: vec3(0.0, 1.0, 0.0),
+            radius: 4.0,
+            tube_radius: 0.5,
+            angle: std::f32::consts::PI,
+        };
+        // the upper half of the ring
+        for angle in [-1.4, -0.7, 0.0, 0.3, 1.5] {
+            let (sin, cos) = f32::sin_cos(angle);
+            assert_near(arc.distance(arc.center + vec3(sin, cos, 0.0) * 4.0), -0.5);
+        }
+        // in the gap the round end caps at (±4, 0) are closest
+        let bottom = arc.center + vec3(0.0, -4.0, 0.0);
+        assert_near(arc.distance(bottom), 32f32.sqrt() - 0.5);
+        let below_end = arc.center + vec3(4.0, -1.0, 0.0);
+        assert_near(arc.distance(below_end), 0.5);
+    }
+
+    #[test]
+    fn smooth_union_blends_colors_at_the_seam() {
+        let colored = |x: f32, color: Vec3| SdfMaterial {
+            inner: SdfSphere {
+                center: vec3(x, 0.0, 0.0),
+                radius: 1.0,
+            },
+            material: Material {
+                color,
+                ..Material::default()
+            },
+        };
+        let blend = SdfSmoothUnion {
+            a: colored(-1.5, vec3(1.0, 0.0, 0.0)),
+            b: colored(1.5, vec3(0.0, 0.0, 1.0)),
+            k: 1.0,
+        };
+        let (_, seam) = blend.distance_material(Vec3::ZERO);
+        assert!(
+            seam.color.abs_diff_eq(vec3(0.5, 0.0, 0.5), 1e-3),
+            "{}",
+            seam.color
+        );
+        // away from the seam each sphere keeps its own color
+        let (_, red) = blend.distance_material(vec3(-2.5, 0.0, 0.0));
+        assert!(red.color.x > 0.9, "{}", red.color);
+    }
+
+    #[test]
+    fn rgba_buffer_marks_hits_opaque() {
+        let scene = Scene {
+            scene: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 5.0,
+            }
+            .boxed(),
+            ..scene(0.0)
+        };
+        let pixels = render_rgba(&scene, &RenderSettings::default(), 7, 5);
+        assert_eq!(pixels.len(), 7 * 5 * 4);
+        let center = (2 * 7 + 3) * 4;
+        assert_eq!(pixels[center + 3], 255);
+        // the corner misses the sphere
+        assert_eq!(pixels[3], 0);
+    }
+
+    #[test]
+    fn height_field_flat_plane_and_grid_interpolation() {
+        let flat = SdfHeightField {
+            height: Box::new(|_, _| 2.0),
+            lipschitz: 0.0,
+        };
+        let plane = SdfPlane {
+            normal: Vec3::Y,
+            offset: -2.0,
+        };
+        for pt in [
+            vec3(0.0, 5.0, 0.0),
+            vec3(-7.0, 2.0, 3.0),
+            vec3(4.0, -1.0, 9.0),
+        ] {
+            assert_near(flat.distance(pt), plane.distance(pt));
+        }
+
+        // 3x2 grid with cells of 2 units
+        let grid = SdfHeightField::grid(vec![0.0, 1.0, 1.0, 2.0, 3.0, 1.0], 3, 2.0);
+        let height = |x: f32, z: f32| (grid.height)(x, z);
+        assert_near(height(2.0, 2.0), 3.0);
+        assert_near(height(1.0, 0.0), 0.5);
+        assert_near(height(1.0, 1.0), 1.5);
+        assert_near(height(3.0, 2.0), 2.0);
+        // the edge heights continue outside the grid
+        assert_near(height(-5.0, 9.0), 2.0);
+        assert_near(grid.distance(vec3(2.0, 3.0, 2.0)), 0.0);
+    }
+
+    #[test]
+    fn camera_basis_cases() {
+        #[track_caller]
+        fn check(pos: Vec3, look_at: Vec3, expected: [Vec3; 3]) {
+            let (right, up, forward) = camera_basis(pos, look_at, Vec3::Y);
+            for (axis, expected) in [right, up, forward].into_iter().zip(expected) {
+                assert!(axis.abs_diff_eq(expected, 1e-5), "{axis} vs {expected}");
+            }
+            // same handedness as the world axes seen from +Z
+            assert!(right.cross(up).abs_diff_eq(-forward, 1e-5));
+        }
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+
+        // straight ahead
+        check(
+            vec3(0.0, 0.0, 20.0),
+            Vec3::ZERO,
+            [Vec3::X, Vec3::Y, Vec3::NEG_Z],
+        );
+        // looking up from below, tilted back by 45 degrees
+        check(
+            vec3(0.0, -5.0, 5.0),
+            Vec3::ZERO,
+            [
+                Vec3::X,
+                vec3(0.0, diagonal, diagonal),
+                vec3(0.0, diagonal, -diagonal),
+            ],
+        );
+        // looking down from the side
+        check(
+            vec3(5.0, 5.0, 0.0),
+            Vec3::ZERO,
+            [
+                vec3(0.0, 0.0, -1.0),
+                vec3(-diagonal, diagonal, 0.0),
+                vec3(-diagonal, -diagonal, 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn step_budget_stops_the_frame_early() {
+        let mut scene = scene(0.7);
+        scene.background = Background::Solid(0.4);
+        let render = |step_budget| {
+            let settings = RenderSettings {
+                step_budget,
+                ..RenderSettings::default()
+            };
+            render_rows(&scene, &settings, 40, 20, 0.5, |_, pixels| {
+                pixels.collect::<Vec<_>>()
+            })
+        };
+        let (full, partial) = render(None);
+        assert!(!partial);
+        assert_eq!(render(Some(1_000_000)), (full.clone(), false));
+
+        let (cut_short, partial) = render(Some(5000));
+        assert!(partial);
+        assert_ne!(cut_short, full);
+
+        // with nothing to spend every pixel shows the background
+        let (empty, partial) = render(Some(0));
+        assert!(partial);
+        assert!(
+            empty
+                .concat()
+                .iter()
+                .all(|&color| color == Vec3::splat(0.4))
+        );
+    }
+
+    #[test]
+    fn dither_breaks_up_flat_gray() {
+        let mut scene = scene(0.0);
+        let cells = |scene: &Scene| {
+            let mut indices: Vec<usize> = (0..8)
+                .flat_map(|y| (0..8).map(move |x| (x, y)))
+                .map(|(x, y)| DEFAULT_SYMBOLS.find(symbol(scene, 0.45, x, y)).unwrap())
+                .collect();
+            indices.sort();
+            indices.dedup();
+            indices
+        };
+        assert_eq!(cells(&scene).len(), 1);
+        scene.dither = true;
+        let dithered = cells(&scene);
+        assert!(dithered.len() >= 2, "{dithered:?}");
+        assert!(dithered.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        // the pattern is fixed per cell
+        assert_eq!(symbol(&scene, 0.45, 3, 1), symbol(&scene, 0.45, 7, 5));
+    }
+
+    #[test]
+    fn region_matches_the_full_frame() {
+        let mut scene = scene(0.7);
+        // the dither pattern has to follow the frame coordinates too
+        scene.dither = true;
+        let settings = RenderSettings::default();
+        let full = render_scene(&scene, &settings, 40, 16, 0.5);
+        let tile = render_region(13, 3, 29, 11, &scene, &settings, 40, 16, 0.5);
+        let expected: Vec<String> = full
+            .lines()
+            .skip(3)
+            .take(8)
+            .map(|line| line.chars().skip(13).take(16).collect())
+            .collect();
+        assert_eq!(tile, expected.join("\n"));
+    }
+
+    #[test]
+    fn interlaced_frame_converges_and_shares_the_budget() {
+        let scene = scene(0.7);
+        let plain = RenderSettings::default();
+        let interlaced = RenderSettings {
+            interlace: true,
+            ..plain
+        };
+        let expected = render_scene(&scene, &plain, 40, 20, 0.5);
+        let mut buffer = String::new();
+        let mut passes = Vec::new();
+        let partial =
+            render_scene_progressive(&mut buffer, &scene, &interlaced, 40, 20, 0.5, |pass| {
+                passes.push(pass.to_string())
+            });
+        assert!(!partial);
+        assert_eq!(passes.len(), 2);
+        assert_ne!(passes[0], expected);
+        assert_eq!(passes[1], expected);
+        assert_eq!(buffer, expected);
+
+        // a budget too small for the whole frame runs out across both passes together
+        let budgeted = |settings: RenderSettings| {
+            let settings = RenderSettings {
+                step_budget: Some(5000),
+                ..settings
+            };
+            render_scene_into(&mut String::new(), &scene, &settings, 40, 20, 0.5)
+        };
+        assert!(budgeted(plain));
+        assert!(budgeted(interlaced));
+    }
+
+    #[test]
+    fn lipschitz_bound_stops_twist_overshoot() {
+        let twisted = || SdfTwist {
+            inner: SdfBox {
+                center: Vec3::ZERO,
+                half_size: vec3(6.0, 20.0, 0.3),
+            },
+            amount: 0.4,
+        };
+        let settings = RenderSettings::default();
+        let start = vec3(0.0, 15.0, 8.0);
+        let ray = vec3(0.0, -1.0, -0.3).normalize();
+        // first point along the ray found inside by sampling it finely
+        let reference = (0..20_000)
+            .map(|step| step as f32 * 0.001)
+            .find(|&t| twisted().distance(start + ray * t) < 0.0)
+            .unwrap();
+
+        let plain = cast_ray(&twisted(), &settings, start, ray);
+        assert!(plain.distance > reference + 1.0, "{}", plain.distance);
+        let bounded = SdfLipschitz {
+            inner: twisted(),
+            bound: 3.0,
+        };
+        let safe = cast_ray(&bounded, &settings, start, ray);
+        assert!(safe.hit);
+        assert!(
+            (safe.distance - reference).abs() < 0.05,
+            "{}",
+            safe.distance
+        );
+    }
+
+    #[test]
+    fn upward_normals_get_the_sky_ambient() {
+        let sky = vec3(0.2, 0.4, 0.8);
+        let ground = vec3(0.3, 0.2, 0.1);
+        let mut scene = Scene {
+            scene: Vec::<Box<dyn Sdf>>::new().boxed(),
+            lights: Vec::new(),
+            ambient_sky: sky,
+            ambient_ground: ground,
+            ao_samples: 0,
+            ..scene(0.0)
+        };
+        let ambient = |scene: &Scene, normal: Vec3| {
+            let settings = RenderSettings::default();
+            shade(
+                scene,
+                &settings,
+                Vec3::ZERO,
+                normal,
+                Material::default(),
+                -normal,
+            )
+        };
+        assert!(ambient(&scene, Vec3::Y).abs_diff_eq(sky, 1e-5));
+        assert!(ambient(&scene, Vec3::NEG_Y).abs_diff_eq(ground, 1e-5));
+        assert!(ambient(&scene, Vec3::X).abs_diff_eq((sky + ground) * 0.5, 1e-5));
+
+        scene.ambient_sky = Vec3::splat(0.1);
+        scene.ambient_ground = Vec3::splat(0.1);
+        for normal in [Vec3::Y, Vec3::NEG_Y, Vec3::Z] {
+            assert!(ambient(&scene, normal).abs_diff_eq(Vec3::splat(0.1), 1e-5));
+        }
+    }
+
+    #[test]
+    fn vesica_matches_two_intersected_spheres() {
+        let center = vec3(1.0, -1.0, 2.0);
+        let vesica = SdfVesica {
+            center,
+            radius: 3.0,
+            separation: 2.0,
+        };
+        let sphere = |z: f32| SdfSphere {
+            center: center + vec3(0.0, 0.0, z),
+            radius: 3.0,
+        };
+        let lens = sphere(-1.0).intersect(sphere(1.0));
+        for offset in [
+            Vec3::ZERO,
+            vec3(1.0, 0.5, 0.3),
+            vec3(0.0, 0.0, 2.0),
+            vec3(0.0, 0.0, -5.0),
+            vec3(0.5, -1.5, 3.0),
+            vec3(2.0, 0.0, 0.5),
+        ] {
+            assert_near(
+                vesica.distance(center + offset),
+                lens.distance(center + offset),
+            );
+        }
+        // next to the sharp rim the intersection only gives a lower bound
+        let beside_rim = center + vec3(4.0, 0.0, 0.0);
+        assert!(vesica.distance(beside_rim) >= lens.distance(beside_rim));
+        assert_near(vesica.distance(beside_rim), 4.0 - 8f32.sqrt());
+    }
+
+    #[test]
+    fn back_faces_get_only_the_ambient_floor() {
+        let mut scene = Scene {
+            scene: sphere_over_plane().boxed(),
+            lights: vec![Light::Directional {
+                direction: Vec3::NEG_Y,
+                color: Vec3::ONE,
+                intensity: 1.0,
+            }],
+            ..scene(0.0)
+        };
+        let lit_from_below = ground_color(&scene, vec3(10.0, 0.0, 0.0));
+        assert!(
+            lit_from_below.abs_diff_eq(Vec3::splat(0.1), 1e-3),
+            "{lit_from_below}"
+        );
+        scene.ambient_sky = Vec3::ZERO;
+        scene.ambient_ground = Vec3::ZERO;
+        assert_eq!(ground_color(&scene, vec3(10.0, 0.0, 0.0)), Vec3::ZERO);
+    }
+
+    #[test]
+    fn batch_matches_single_points_for_every_primitive() {
+        let primitives: Vec<Box<dyn Sdf>> = vec![
+            SdfSphere {
+                center: vec3(0.5, 0.0, 0.0),
+                radius: 2.0,
+            }
+            .boxed(),
+            SdfBox {
+                center: Vec3::ZERO,
+                half_size: vec3(1.0, 2.0, 3.0),
+            }
+            .boxed(),
+            SdfRoundedBox {
+                center: Vec3::ZERO,
+                half_size: vec3(1.0, 2.0, 3.0),
+                radius: 0.5,
+            }
+            .boxed(),
+            SdfDonut::new(Vec3::ZERO, 3.0, 1.0).boxed(),
+            SdfCappedTorus {
+                center: Vec3::ZERO,
+                radius: 3.0,
+                tube_radius: 0.5,
+                angle: 2.0,
+            }
+            .boxed(),
+            SdfCylinder {
+                center: Vec3::ZERO,
+                radius: 1.0,
+                half_height: 2.0,
+            }
+            .boxed(),
+            SdfCapsule {
+                a: Vec3::ZERO,
+                b: vec3(1.0, 2.0, 0.0),
+                radius: 0.5,
+            }
+            .boxed(),
+            SdfRoundCone {
+                a: Vec3::ZERO,
+                b: vec3(0.0, 3.0, 0.0),
+                r1: 1.0,
+                r2: 0.5,
+            }
+            .boxed(),
+            SdfPlane {
+                normal: Vec3::Y,
+                offset: 1.0,
+            }
+            .boxed(),
+            SdfCone {
+                center: Vec3::ZERO,
+                angle: 0.5,
+                height: 2.0,
+            }
+            .boxed(),
+            SdfEllipsoid {
+                center: Vec3::ZERO,
+                radii: vec3(1.0, 2.0, 3.0),
+            }
+            .boxed(),
+            SdfOctahedron {
+                center: Vec3::ZERO,
+                size: 2.0,
+            }
+            .boxed(),
+            SdfTriPrism {
+                center: Vec3::ZERO,
+                size: Vec2::new(1.0, 2.0),
+            }
+            .boxed(),
+            SdfHexPrism {
+                center: Vec3::ZERO,
+                radius: 1.0,
+                half_height: 2.0,
+            }
+            .boxed(),
+            SdfPyramid {
+                center: Vec3::ZERO,
+                base: 2.0,
+                height: 3.0,
+            }
+            .boxed(),
+            SdfSolidAngle {
+                center: Vec3::ZERO,
+                angle: 0.6,
+                radius: 3.0,
+            }
+            .boxed(),
+            SdfVesica {
+                center: Vec3::ZERO,
+                radius: 2.0,
+                separation: 1.0,
+            }
+            .boxed(),
+            SdfHeightField {
+                height: Box::new(|x, z| (x * z).sin()),
+                lipschitz: 2.0,
+            }
+            .boxed(),
+            crate::SdfChar {
+                ch: 'B',
+                position: vec3(-1.0, -1.5, 0.0),
+                pixel_size: 0.5,
+                depth: 1.0,
+            }
+            .boxed(),
+            crate::SdfText {
+                text: "Hi!".to_string(),
+                position: vec3(-2.0, -1.5, 0.0),
+                pixel_size: 0.4,
+                depth: 1.0,
+            }
+            .boxed(),
+        ];
+        assert_batch_matches_single_points(&primitives);
+    }
+
+    /// Batch distances of every shape at points spread over more than one forwarded chunk.
+    #[track_caller]
+    fn assert_batch_matches_single_points(shapes: &[Box<dyn Sdf>]) {
+        let points: Vec<Vec3> = (0..27)
+            .map(|i| vec3((i % 3) as f32, (i / 3 % 3) as f32, (i / 9) as f32) * 1.3 - 1.2)
+            .collect();
+        for (index, shape) in shapes.iter().enumerate() {
+            let mut distances = vec![0.0; points.len()];
+            shape.distance_batch(&points, &mut distances);
+            for (pt, distance) in points.iter().zip(distances) {
+                assert_eq!(distance, shape.distance(*pt), "shape {index} at {pt}");
+            }
+        }
+    }
+
+    #[test]
+    fn combinators_forward_batches() {
+        let sphere = || SdfSphere {
+            center: vec3(0.5, 0.0, 0.0),
+            radius: 1.5,
+        };
+        let cube = || SdfBox {
+            center: vec3(-0.5, 0.5, 0.0),
+            half_size: Vec3::splat(1.0),
+        };
+        let combinators: Vec<Box<dyn Sdf>> = vec![
+            SdfTransform {
+                mat: Mat4::from_rotation_y(0.7) * Mat4::from_translation(vec3(0.3, -0.2, 0.1)),
+                inner: sphere(),
+                distance_scale: 0.8,
+            }
+            .boxed(),
+            SdfScale {
+                inner: cube(),
+                factor: 1.7,
+            }
+            .boxed(),
+            SdfMaterial {
+                inner: cube(),
+                material: Material::default(),
+            }
+            .boxed(),
+            sphere().union(cube()).boxed(),
+            SdfSmoothUnion {
+                a: sphere(),
+                b: cube(),
+                k: 0.8,
+            }
+            .boxed(),
+            sphere().subtract(cube()).boxed(),
+            sphere().intersect(cube()).boxed(),
+            vec![sphere().boxed(), cube().boxed()].boxed(),
+            [
+                sphere(),
+                SdfSphere {
+                    center: vec3(-1.0, 0.5, 0.0),
+                    radius: 0.5,
+                },
+            ]
+            .boxed(),
+        ];
+        assert_batch_matches_single_points(&combinators);
+
+        // forwarding reaches the batch override of the glyph inside the transform
+        let text = Counting::new(crate::SdfText {
+            text: "ok".to_string(),
+            position: vec3(-1.0, -1.0, 0.0),
+            pixel_size: 0.3,
+            depth: 1.0,
+        });
+        let moved = SdfTransform::translate(text, Vec3::X).union(sphere());
+        moved.distance_batch(&[Vec3::ZERO; 20], &mut [0.0; 20]);
+        // 20 points go through in chunks of 8
+        assert_eq!(moved.a.inner.calls(), 20);
+        assert_eq!(moved.a.inner.batches(), 3);
+    }
+
+    #[test]
+    fn forward_normals_agree_with_central_in_fewer_calls() {
+        let normal = |normal_method| {
+            let sphere = Counting::new(SdfSphere {
+                center: Vec3::ZERO,
+                radius: 2.0,
+            });
+            let settings = RenderSettings {
+                normal_method,
+                normal_epsilon: 0.001,
+                ..RenderSettings::default()
+            };
+            let point = vec3(1.0, 1.0, 2f32.sqrt());
+            (estimate_normal(&sphere, &settings, point), sphere.calls())
+        };
+        let (central, central_calls) = normal(NormalMethod::Central);
+        let (forward, forward_calls) = normal(NormalMethod::Forward);
+        assert!(forward.abs_diff_eq(central, 1e-3), "{forward} vs {central}");
+        assert!(central.abs_diff_eq(vec3(1.0, 1.0, 2f32.sqrt()) / 2.0, 1e-3));
+        assert_eq!((forward_calls, central_calls), (4, 6));
+    }
+
+    #[test]
+    fn solid_angle_inside_and_beside_the_cone() {
+        let sector = SdfSolidAngle {
+            center: vec3(0.0, 1.0, 0.0),
+            angle: std::f32::consts::FRAC_PI_4,
+            radius: 4.0,
+        };
+        let at = |x: f32, y: f32, z: f32| sector.distance(sector.center + vec3(x, y, z));
+        assert!(at(0.0, 2.0, 0.0) < 0.0);
+        assert!(at(0.5, 2.0, -0.5) < 0.0);
+        // beyond the cap
+        assert_near(at(0.0, 5.0, 0.0), 1.0);
+        // outside the sweep the nearest cone edge decides, in any direction around Y
+        assert_near(at(3.0, 1.0, 0.0), 2f32.sqrt());
+        assert_near(at(0.0, 1.0, -3.0), 2f32.sqrt());
+        // below the apex
+        assert_near(at(0.0, -2.0, 0.0), 2.0);
+    }
+
+    #[test]
+    fn reinhard_keeps_highlights_below_the_ramp_max() {
+        let scene = scene(0.0);
+        let brightest = DEFAULT_SYMBOLS.chars().last().unwrap();
+        let mapped = |tone_map: ToneMap| {
+            let color = tone_map.apply(Vec3::splat(3.0));
+            symbol(&scene, luminance(color), 0, 0)
+        };
+        assert_eq!(mapped(ToneMap::None), brightest);
+        assert_ne!(mapped(ToneMap::Reinhard), brightest);
+        assert!(
+            ToneMap::Reinhard
+                .apply(Vec3::splat(3.0))
+                .abs_diff_eq(Vec3::splat(0.75), 1e-6)
+        );
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_frame() {
+        let render = |seed: u64| {
+            let spheres = scatter(
+                12,
+                seed,
+                Vec3::splat(-10.0),
+                Vec3::splat(10.0),
+                1.0..3.0,
+                |center, radius| SdfSphere { center, radius },
+            );
+            let scene = Scene {
+                scene: spheres.boxed(),
+                ..scene(0.0)
+            };
+            render_rgba(&scene, &RenderSettings::default(), 24, 16)
+        };
+        assert_eq!(render(7), render(7));
+        assert_ne!(render(7), render(8));
+    }
+
+    #[test]
+    fn interpolate_blends_between_the_shapes() {
+        let morph = |t: f32| SdfInterpolate {
+            a: SdfSphere {
+                center: Vec3::ZERO,
+                radius: 2.0,
+            },
+            b: SdfBox {
+                center: Vec3::ZERO,
+                half_size: Vec3::splat(1.5),
+            },
+            t,
+        };
+        for pt in [Vec3::ZERO, vec3(2.0, 2.0, 0.0), vec3(0.5, -3.0, 1.0)] {
+            let (a, b) = (morph(0.0).a.distance(pt), morph(0.0).b.distance(pt));
+            assert_near(morph(0.0).distance(pt), a);
+            assert_near(morph(1.0).distance(pt), b);
+            let half = morph(0.5).distance(pt);
+            assert!(
+                a.min(b) <= half && half <= a.max(b),
+                "{half} between {a} and {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn aperture_spreads_origins_but_keeps_the_focus_sharp() {
+        let scene = Scene {
+            projection: Projection::Perspective { fov_y_radians: 1.0 },
+            ..scene(0.0)
+        };
+        let camera = Camera::new(&scene, 9, 9, 1.0);
+        let (origin, ray) = camera.ray(2.0, 6.0);
+        let focus = origin + ray * (scene.focal_distance / ray.dot(camera.forward));
+        let spread = |aperture: f32| {
+            let rays: Vec<(Vec3, Vec3)> = (0..LENS_SAMPLES)
+                .map(|index| {
+                    let lens = lens_offset(index, aperture);
+                    camera.lens_ray(origin, ray, lens, scene.focal_distance)
+                })
+                .collect();
+            for (lens_origin, lens_ray) in &rays {
+                // every ray still passes through the point in focus
+                let closest = lens_origin + lens_ray * (focus - lens_origin).dot(*lens_ray);
+                assert!(closest.distance(focus) < 1e-4, "{closest} vs {focus}");
+            }
+            let mean = rays.iter().map(|(o, _)| *o).sum::<Vec3>() / rays.len() as f32;
+            rays.iter()
+                .map(|(o, _)| o.distance_squared(mean))
+                .sum::<f32>()
+                / rays.len() as f32
+        };
+        let (none, small, large) = (spread(0.0), spread(0.5), spread(2.0));
+        assert_near(none, 0.0);
+        assert!(none < small && small < large, "{none} {small} {large}");
+    }
+
+    #[test]
+    fn explicit_union_matches_the_iterator_union() {
+        let sphere = || SdfSphere {
+            center: vec3(-2.0, 0.0, 0.0),
+            radius: 1.5,
+        };
+        let cube = || SdfBox {
+            center: vec3(2.0, 1.0, 0.0),
+            half_size: Vec3::ONE,
+        };
+        let union = sphere().union(cube());
+        let listed = vec![sphere().boxed(), cube().boxed()];
+        for i in 0..20 {
+            let pt = vec3(i as f32 * 0.5 - 5.0, (i % 4) as f32 - 1.5, (i % 3) as f32);
+            assert_eq!(union.distance(pt), listed.distance(pt), "at {pt}");
+        }
+    }
+
+    #[test]
+    fn emissive_sphere_is_fully_bright_facing_away_from_the_light() {
+        let frame = |shape: Box<dyn Sdf>| {
+            let scene = Scene {
+                scene: shape,
+                // behind the sphere as seen from the camera
+                lights: vec![Light::Directional {
+                    direction: Vec3::NEG_Z,
+                    color: Vec3::ONE,
+                    intensity: 1.0,
+                }],
+                projection: Projection::Orthographic { size: 12.0 },
+                ..scene(0.0)
+            };
+            render_scene(&scene, &RenderSettings::default(), 9, 9, 1.0)
+        };
+        let sphere = || SdfSphere {
+            center: Vec3::ZERO,
+            radius: 5.0,
+        };
+        let glowing = frame(glowing(sphere()));
+        assert!(glowing.contains('@'));
+        assert!(
+            glowing.chars().all(|ch| matches!(ch, ' ' | '\n' | '@')),
+            "{glowing}"
+        );
+        assert!(!frame(sphere().boxed()).contains('@'));
+    }
+
+    #[test]
+    fn rim_light_brightens_the_limb() {
+        let scene = Scene {
+            scene: Vec::<Box<dyn Sdf>>::new().boxed(),
+            lights: Vec::new(),
+            ambient_sky: Vec3::ZERO,
+            ambient_ground: Vec3::ZERO,
+            ao_samples: 0,
+            rim_strength: 0.8,
+            ..scene(0.0)
+        };
+        let rim = |normal: Vec3| {
+            let settings = RenderSettings::default();
+            shade(
+                &scene,
+                &settings,
+                Vec3::ZERO,
+                normal,
+                Material::default(),
+                Vec3::NEG_Z,
+            )
+            .x
+        };
+        let head_on = rim(Vec3::Z);
+        let limb = rim(vec3(1.0, 0.0, 0.1).normalize());
+        assert_near(head_on, 0.0);
+        assert!(limb > 0.5, "{limb}");
+    }
+
+    #[test]
+    fn round_cone_ends_and_degenerate_axis() {
+        let cone = SdfRoundCone {
+            a: vec3(1.0, 0.0, 0.0),
+            b: vec3(1.0, 5.0, 2.0),
+            r1: 1.5,
+            r2: 0.5,
+        };
+        assert_near(cone.distance(cone.a), -1.5);
+        assert_near(cone.distance(cone.b), -0.5);
+
+        let point = SdfRoundCone { b: cone.a, ..cone };
+        assert_near(point.distance(cone.a), -1.5);
+        assert_near(point.distance(cone.a + vec3(0.0, 4.0, 0.0)), 2.5);
+        let flipped = SdfRoundCone {
+            r1: 0.5,
+            r2: 1.5,
+            ..point
+        };
+        assert_near(flipped.distance(cone.a), -1.5);
+    }
+
+    #[test]
+    fn sphere_bounds_are_a_cube_around_it() {
+        let sphere = SdfSphere {
+            center: vec3(3.0, -1.0, 2.0),
+            radius: 1.5,
+        };
+        let (min, max) = sphere.bounds().unwrap();
+        assert!((max - min).abs_diff_eq(Vec3::splat(3.0), 1e-6));
+        assert!(((min + max) * 0.5).abs_diff_eq(sphere.center, 1e-6));
+        assert_eq!(
+            SdfPlane {
+                normal: Vec3::Y,
+                offset: 0.0
+            }
+            .bounds(),
+            None
+        );
+    }
+
+    #[test]
+    fn thin_walls_glow_more_than_thick_ones() {
+        let front_face = |thickness: f32| {
+            let scene = Scene {
+                scene: SdfBox {
+                    center: vec3(0.0, 0.0, -thickness * 0.5),
+                    half_size: vec3(5.0, 5.0, thickness * 0.5),
+                }
+                .boxed(),
+                // shining at the back face
+                lights: vec![Light::Directional {
+                    direction: Vec3::NEG_Z,
+                    color: Vec3::ONE,
+                    intensity: 1.0,
+                }],
+                ao_samples: 0,
+                translucency: 0.8,
+                ..scene(0.0)
+            };
+            let settings = RenderSettings::default();
+            shade(
+                &scene,
+                &settings,
+                Vec3::ZERO,
+                Vec3::Z,
+                Material::default(),
+                Vec3::NEG_Z,
+            )
+            .x
+        };
+        let thin = front_face(0.1);
+        let thick = front_face(3.0);
+        assert!(thin > thick + 0.1, "{thin} vs {thick}");
+        assert!(thick > 0.1);
     }
 }
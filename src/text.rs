@@ -0,0 +1,222 @@
+use crate::{Sdf, SdfBox, for_batch_chunks};
+use glam::{Vec3, vec3};
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+// glyphs are separated by one empty column
+const GLYPH_ADVANCE: f32 = 6.0;
+
+/// 5x7 bitmap of `ch`, rows from top to bottom with the leftmost column in the highest bit.
+/// Lowercase letters use the uppercase glyphs, characters without a glyph show as `?`.
+fn glyph(ch: char) -> [u8; GLYPH_HEIGHT] {
+    match ch.to_ascii_uppercase() {
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1e],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        ' ' => [0x00; GLYPH_HEIGHT],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        _ => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// Character from a built-in 5x7 pixel font, extruded along Z.
+/// `position` is the bottom left corner of the glyph in the middle of its depth,
+/// every font pixel is a `pixel_size` square.
+pub struct SdfChar {
+    pub ch: char,
+    pub position: Vec3,
+    pub pixel_size: f32,
+    pub depth: f32,
+}
+
+impl SdfChar {
+    fn bounding_box(&self) -> SdfBox {
+        let half_size = vec3(
+            GLYPH_WIDTH as f32 * 0.5 * self.pixel_size,
+            GLYPH_HEIGHT as f32 * 0.5 * self.pixel_size,
+            self.depth * 0.5,
+        );
+        SdfBox {
+            center: self.position + vec3(half_size.x, half_size.y, 0.0),
+            half_size,
+        }
+    }
+
+    /// Calls `f` with a box for every horizontal run of lit pixels of the glyph.
+    fn for_each_run(&self, mut f: impl FnMut(SdfBox)) {
+        for (row, bits) in glyph(self.ch).into_iter().enumerate() {
+            // each horizontal run of lit pixels becomes a single box
+            let mut column = 0;
+            while column < GLYPH_WIDTH {
+                let lit = |column: usize| bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0;
+                if !lit(column) {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < GLYPH_WIDTH && lit(column) {
+                    column += 1;
+                }
+                let min = vec3(start as f32, (GLYPH_HEIGHT - 1 - row) as f32, 0.0);
+                let max = vec3(column as f32, (GLYPH_HEIGHT - row) as f32, 0.0);
+                f(SdfBox {
+                    center: self.position + (min + max) * 0.5 * self.pixel_size,
+                    half_size: ((max - min) * 0.5 * self.pixel_size).with_z(self.depth * 0.5),
+                });
+            }
+        }
+    }
+}
+
+impl Sdf for SdfChar {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let mut distance = f32::INFINITY;
+        self.for_each_run(|run| distance = distance.min(run.distance(pt)));
+        distance
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        assert_eq!(points.len(), distances.len());
+        distances.fill(f32::INFINITY);
+        // the glyph is decoded once for all the points
+        self.for_each_run(|run| {
+            for (pt, distance) in points.iter().zip(distances.iter_mut()) {
+                *distance = distance.min(run.distance(*pt));
+            }
+        });
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let bounds = self.bounding_box();
+        Some((
+            bounds.center - bounds.half_size,
+            bounds.center + bounds.half_size,
+        ))
+    }
+}
+
+/// Line of [`SdfChar`]s laid out along +X starting at `position`, one empty font pixel apart.
+pub struct SdfText {
+    pub text: String,
+    pub position: Vec3,
+    pub pixel_size: f32,
+    pub depth: f32,
+}
+
+impl SdfText {
+    fn chars(&self) -> impl Iterator<Item = SdfChar> + '_ {
+        self.text.chars().enumerate().map(|(index, ch)| SdfChar {
+            ch,
+            position: self.position + Vec3::X * (index as f32 * GLYPH_ADVANCE * self.pixel_size),
+            pixel_size: self.pixel_size,
+            depth: self.depth,
+        })
+    }
+}
+
+impl Sdf for SdfText {
+    fn distance(&self, pt: Vec3) -> f32 {
+        let mut distance = f32::INFINITY;
+        for ch in self.chars() {
+            // a glyph is never closer than its bounding box
+            if ch.bounding_box().distance(pt) < distance {
+                distance = distance.min(ch.distance(pt));
+            }
+        }
+        distance
+    }
+
+    fn distance_batch(&self, points: &[Vec3], distances: &mut [f32]) {
+        for_batch_chunks(points, distances, |points, distances, char_distances| {
+            distances.fill(f32::INFINITY);
+            for ch in self.chars() {
+                let bounding_box = ch.bounding_box();
+                let closer = |(pt, distance): (&Vec3, &f32)| bounding_box.distance(*pt) < *distance;
+                if !points.iter().zip(distances.iter()).any(closer) {
+                    continue;
+                }
+                ch.distance_batch(points, char_distances);
+                for (distance, ch) in distances.iter_mut().zip(&*char_distances) {
+                    *distance = distance.min(*ch);
+                }
+            }
+        });
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let first = self.chars().next()?;
+        let last = self.chars().last()?;
+        let (min, _) = first.bounds()?;
+        let (_, max) = last.bounds()?;
+        Some((min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strokes_are_solid_and_gaps_are_empty() {
+        let t = SdfChar {
+            ch: 'T',
+            position: Vec3::ZERO,
+            pixel_size: 1.0,
+            depth: 2.0,
+        };
+        // on the front face of the top bar and of the stem
+        assert!(t.distance(vec3(0.5, 6.5, 1.0)).abs() < 1e-5);
+        assert!(t.distance(vec3(2.5, 3.0, 1.0)).abs() < 1e-5);
+        assert!(t.distance(vec3(2.5, 3.5, 0.0)) < 0.0);
+        // under the bar, left of the stem
+        assert!((t.distance(vec3(0.5, 3.0, 0.0)) - 1.5).abs() < 1e-5);
+
+        let text = SdfText {
+            text: "IT".to_string(),
+            position: Vec3::ZERO,
+            pixel_size: 1.0,
+            depth: 2.0,
+        };
+        // the stem of the T sits one glyph advance to the right
+        assert!(text.distance(vec3(8.5, 3.5, 0.0)) < 0.0);
+        assert!(text.distance(vec3(6.5, 3.0, 0.0)) > 0.0);
+    }
+}